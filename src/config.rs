@@ -1,12 +1,34 @@
+use std::collections::HashMap;
 use std::fs;
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use crate::epsilon::queue::common::placement::PlacementStrategy;
+
 #[derive(Serialize, Deserialize)]
 pub struct EpsilonConfig {
     pub proxy: ProxyConfig,
     pub hub: HubConfig,
+
+    #[serde(default)]
+    pub reservation: ReservationConfig,
+
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    #[serde(default)]
+    pub events: EventsConfig,
+
+    #[serde(default)]
+    pub transfer: TransferConfig,
+
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+
+    #[serde(default)]
+    pub queue: QueueConfig,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,6 +43,306 @@ pub struct HubConfig {
     pub minimum_hubs: u8,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct ReservationConfig {
+    pub ttl: u64,
+}
+
+impl Default for ReservationConfig {
+    fn default() -> Self {
+        Self { ttl: 30 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    // Disabling it grants admin access to anything that can reach the api
+    pub enabled: bool,
+
+    pub file: Option<String>,
+    pub secret: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+
+            file: None,
+            secret: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EventsConfig {
+    pub replay: usize,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self { replay: 4096 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferConfig {
+    // Without tracking, a transfer is considered done as soon as it is sent
+    pub track: bool,
+
+    pub timeout: u64,
+    pub attempts: u32,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            track: true,
+
+            timeout: 10,
+            attempts: 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub subscriptions: Vec<WebhookSubscription>,
+
+    pub attempts: u32,
+    pub backoff: u64,
+    pub dead_letter: String,
+
+    // Deliveries of a subscription retried at the same time, later events are dead lettered
+    pub max_pending: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WebhookSubscription {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+    pub secret: Option<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            subscriptions: Vec::new(),
+
+            attempts: 5,
+            backoff: 500,
+            dead_letter: String::from("./webhook_dead_letter.log"),
+
+            max_pending: 256,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    pub single_queue: bool,
+    pub priority: PriorityConfig,
+
+    pub placement: PlacementStrategy,
+    pub max_skips: u32,
+
+    // Seconds between two position checks, a QueuePosition event is only sent when it changed
+    pub position_interval: u64,
+
+    // Seconds a targeted group waits for its instance before joining any instance of the queue
+    pub target_timeout: u64,
+
+    // Seconds a match instance has to start before its players go back to the queue
+    pub match_start_timeout: u64,
+
+    // Queues spread over several templates, in addition to the queue of each template
+    pub templates: HashMap<String, Vec<WeightedTemplate>>,
+
+    pub limits: LimitsConfig,
+
+    pub expiry: ExpiryConfig,
+    pub persistence: PersistenceConfig,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PriorityConfig {
+    pub ranks: HashMap<String, i32>,
+
+    pub policy: PriorityPolicy,
+    pub queues: HashMap<String, PriorityPolicy>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    // Pushes allowed for each player and for each caller
+    pub player: RateLimit,
+    pub caller: RateLimit,
+
+    // Players a queue can hold, no limit when unset or 0
+    pub max_length: Option<usize>,
+    pub queues: HashMap<String, usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RateLimit {
+    // No limit when 0
+    pub requests: u32,
+    pub period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WeightedTemplate {
+    pub template: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ExpiryConfig {
+    // Seconds a group can wait in a queue before being removed, no limit when unset or 0
+    pub max_wait: Option<u64>,
+    pub queues: HashMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistenceConfig {
+    pub backend: PersistenceBackend,
+
+    pub directory: String,
+    pub config_map: String,
+
+    // Operations logged before the queues are written to a new snapshot
+    pub compact_after: usize,
+
+    // Milliseconds between two writes, operations are written together in the meantime.
+    // The config map backend only keeps snapshots, a crash loses the operations since the last one
+    pub flush_interval: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistenceBackend {
+    Memory,
+    File,
+    ConfigMap,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PriorityPolicy {
+    pub enabled: bool,
+    pub aging: u64,
+    pub max_priority: i32,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            single_queue: true,
+            priority: PriorityConfig::default(),
+
+            placement: PlacementStrategy::default(),
+            max_skips: 3,
+
+            position_interval: 10,
+
+            target_timeout: 60,
+
+            match_start_timeout: 180,
+
+            templates: HashMap::new(),
+
+            limits: LimitsConfig::default(),
+
+            expiry: ExpiryConfig::default(),
+            persistence: PersistenceConfig::default(),
+        }
+    }
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            backend: PersistenceBackend::Memory,
+
+            directory: String::from("./queues"),
+            config_map: String::from("epsilon-queues"),
+
+            compact_after: 1000,
+
+            flush_interval: 1000,
+        }
+    }
+}
+
+impl Default for PriorityPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            aging: 30,
+            max_priority: 100,
+        }
+    }
+}
+
+impl PriorityConfig {
+    pub fn get_policy(&self, queue_name: &str) -> PriorityPolicy {
+        self.queues.get(queue_name).unwrap_or(&self.policy).clone()
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            player: RateLimit {
+                requests: 5,
+                period: 10,
+            },
+            caller: RateLimit {
+                requests: 200,
+                period: 1,
+            },
+
+            max_length: None,
+            queues: HashMap::new(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn get_max_length(&self, queue_name: &str) -> Option<usize> {
+        self.queues
+            .get(queue_name)
+            .copied()
+            .or(self.max_length)
+            .filter(|max_length| *max_length > 0)
+    }
+}
+
+impl ExpiryConfig {
+    pub fn get_max_wait(&self, queue_name: &str) -> Option<Duration> {
+        self.queues
+            .get(queue_name)
+            .copied()
+            .or(self.max_wait)
+            .filter(|max_wait| *max_wait > 0)
+            .map(Duration::from_secs)
+    }
+}
+
 impl Default for EpsilonConfig {
     fn default() -> Self {
         Self {
@@ -32,6 +354,12 @@ impl Default for EpsilonConfig {
                 template: String::from("hub"),
                 minimum_hubs: 1,
             },
+            reservation: ReservationConfig::default(),
+            auth: AuthConfig::default(),
+            events: EventsConfig::default(),
+            transfer: TransferConfig::default(),
+            webhook: WebhookConfig::default(),
+            queue: QueueConfig::default(),
         }
     }
 }
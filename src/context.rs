@@ -1,30 +1,38 @@
 use std::sync::Arc;
 
-use crate::{EpsilonApi, InstanceProvider, QueueProvider, TemplateProvider};
+use crate::{
+    AuthProvider, EpsilonApi, InstanceProvider, QueueProvider, TemplateProvider, TransferProvider,
+};
 
 pub struct Context {
-    epsilon_api: EpsilonApi,
+    epsilon_api: Arc<EpsilonApi>,
     template_provider: Arc<TemplateProvider>,
     instance_provider: InstanceProvider,
     queue_provider: QueueProvider,
+    transfer_provider: TransferProvider,
+    auth_provider: AuthProvider,
 }
 
 impl Context {
     pub fn new(
-        epsilon_api: EpsilonApi,
+        epsilon_api: Arc<EpsilonApi>,
         template_provider: Arc<TemplateProvider>,
         instance_provider: InstanceProvider,
         queue_provider: QueueProvider,
+        transfer_provider: TransferProvider,
+        auth_provider: AuthProvider,
     ) -> Arc<Context> {
         Arc::new(Self {
             epsilon_api,
             template_provider,
             instance_provider,
             queue_provider,
+            transfer_provider,
+            auth_provider,
         })
     }
 
-    pub fn get_epsilon_api(&self) -> &EpsilonApi {
+    pub fn get_epsilon_api(&self) -> &Arc<EpsilonApi> {
         &self.epsilon_api
     }
 
@@ -39,4 +47,12 @@ impl Context {
     pub fn get_queue_provider(&self) -> &QueueProvider {
         &self.queue_provider
     }
+
+    pub fn get_transfer_provider(&self) -> &TransferProvider {
+        &self.transfer_provider
+    }
+
+    pub fn get_auth_provider(&self) -> &AuthProvider {
+        &self.auth_provider
+    }
 }
@@ -1,27 +1,33 @@
 use std::sync::Arc;
 
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{Pod, Secret};
 use kube::Api;
 
 use crate::controller::definitions::epsilon_instance::EpsilonInstance;
-use crate::TemplateProvider;
+use crate::{EpsilonApi, TemplateProvider};
 
 pub struct Context {
     pub pod_api: Api<Pod>,
+    pub secret_api: Api<Secret>,
     pub epsilon_instance_api: Api<EpsilonInstance>,
     pub template_provider: Arc<TemplateProvider>,
+    pub epsilon_api: Arc<EpsilonApi>,
 }
 
 impl Context {
     pub fn new(
         pod_api: Api<Pod>,
+        secret_api: Api<Secret>,
         epsilon_instance_api: Api<EpsilonInstance>,
         template_provider: &Arc<TemplateProvider>,
+        epsilon_api: &Arc<EpsilonApi>,
     ) -> Self {
         Context {
             pod_api,
+            secret_api,
             epsilon_instance_api,
             template_provider: Arc::clone(template_provider),
+            epsilon_api: Arc::clone(epsilon_api),
         }
     }
 }
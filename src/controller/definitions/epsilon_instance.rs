@@ -1,14 +1,19 @@
 use async_trait::async_trait;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use crate::controller::epsilon_controller::ALLOCATED_ANNOTATION;
 use crate::epsilon::server::instances::common::instance_type::InstanceType;
 use crate::epsilon::server::instances::common::state::EpsilonState;
 
 use crate::epsilon::epsilon_error::EpsilonError;
-use async_minecraft_ping::{ConnectionConfig, StatusResponse};
+use crate::epsilon::server::instances::common::allocation::AllocationJson;
+use async_minecraft_ping::{ConnectionConfig, ServerDescription, StatusResponse};
+use futures::future::join_all;
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -21,6 +26,7 @@ use tokio::time::timeout;
     printcolumn = r#"{"name":"Template", "type":"string", "description":"Template name of instance", "jsonPath":".spec.template"}"#,
     printcolumn = r#"{"name":"State", "type":"string", "description":"State of instance", "jsonPath":".status.state"}"#,
     printcolumn = r#"{"name":"Online", "type":"integer", "description":"Online count of instance", "jsonPath":".status.online"}"#,
+    printcolumn = r#"{"name":"Version", "type":"string", "description":"Server version of instance", "jsonPath":".status.version"}"#,
     namespaced
 )]
 pub struct EpsilonInstanceSpec {
@@ -44,7 +50,13 @@ pub struct EpsilonInstanceStatus {
     pub slots: i32,
     pub online: i32,
 
+    pub version: Option<String>,
+    pub protocol: Option<i32>,
+
     pub close: bool,
+
+    #[serde(default)]
+    pub allocated: bool,
 }
 
 impl EpsilonInstance {
@@ -55,6 +67,8 @@ impl EpsilonInstance {
             .ok_or(EpsilonError::RetrieveStatusError)?
             .clone();
 
+        let info = self.get_server_info().await.ok();
+
         Ok(InstanceJson {
             name: self.get_name(),
             template: self.spec.template.clone(),
@@ -67,16 +81,72 @@ impl EpsilonInstance {
             state: self.get_state(),
 
             slots: status.slots,
-            online_count: self.get_online_count().await.unwrap_or(0),
+            online_count: info.as_ref().map(|info| info.online_count).unwrap_or(0),
 
             ip: status.ip,
+
+            version: info.as_ref().map(|info| info.version.clone()),
+            protocol: info.as_ref().map(|info| info.protocol),
+            motd: info.as_ref().map(|info| info.motd.clone()),
+            max_players: info.as_ref().map(|info| info.max_players),
+            players: info.map(|info| info.players),
+        })
+    }
+
+    pub async fn get_server_info(&self) -> Result<ServerInfoJson, EpsilonError> {
+        let info = self.get_info().await?;
+
+        Ok(ServerInfoJson {
+            version: info.version.name,
+            protocol: info.version.protocol as i32,
+
+            motd: match info.description {
+                ServerDescription::Plain(text) => text,
+                ServerDescription::Object { text } => text,
+            },
+
+            max_players: info.players.max as i32,
+            online_count: info.players.online as i32,
+
+            players: info
+                .players
+                .sample
+                .unwrap_or_default()
+                .into_iter()
+                .map(|player| player.name)
+                .collect(),
         })
     }
 
+    pub fn to_allocation_json(&self) -> AllocationJson {
+        let status = self.status.as_ref();
+
+        AllocationJson {
+            name: self.get_name(),
+            template: self.spec.template.clone(),
+
+            state: self.get_state(),
+            allocated: self.is_allocated(),
+
+            ip: status.and_then(|status| status.ip.clone()),
+            port: status
+                .map(|status| status.t)
+                .unwrap_or(InstanceType::Server)
+                .get_entry_port(),
+        }
+    }
+
     pub fn get_name(&self) -> String {
         self.metadata.name.as_ref().unwrap().to_owned()
     }
 
+    pub fn get_cached_online_count(&self) -> i32 {
+        match &self.status {
+            None => 0,
+            Some(status) => status.online,
+        }
+    }
+
     pub fn get_state(&self) -> EpsilonState {
         match &self.status {
             None => EpsilonState::Starting,
@@ -84,6 +154,35 @@ impl EpsilonInstance {
         }
     }
 
+    pub fn is_allocated(&self) -> bool {
+        match &self.status {
+            None => self
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(ALLOCATED_ANNOTATION))
+                .map_or(false, |allocated| allocated == "true"),
+            Some(status) => status.allocated,
+        }
+    }
+
+    pub fn match_labels(&self, labels: &BTreeMap<String, String>) -> bool {
+        let instance_labels = self.metadata.labels.as_ref();
+
+        labels
+            .iter()
+            .all(|(key, value)| instance_labels.and_then(|labels| labels.get(key)) == Some(value))
+    }
+
+    pub fn match_content(&self, content: &Map<String, Value>) -> bool {
+        let instance_content =
+            serde_json::from_str::<Value>(&self.spec.content).unwrap_or(Value::Null);
+
+        content
+            .iter()
+            .all(|(key, value)| instance_content.get(key) == Some(value))
+    }
+
     pub async fn get_info(&self) -> Result<StatusResponse, EpsilonError> {
         let status = self
             .status
@@ -128,6 +227,7 @@ impl EpsilonInstance {
 pub trait VectorOfInstance {
     async fn get_available_slots(&self) -> Result<i32, EpsilonError>;
     async fn get_online_count(&self) -> Result<i32, EpsilonError>;
+    async fn to_json(&self) -> Result<Vec<InstanceJson>, EpsilonError>;
 }
 
 #[async_trait]
@@ -151,9 +251,16 @@ impl VectorOfInstance for Vec<Arc<EpsilonInstance>> {
 
         Ok(number)
     }
+
+    async fn to_json(&self) -> Result<Vec<InstanceJson>, EpsilonError> {
+        join_all(self.iter().map(|instance| instance.to_json()))
+            .await
+            .into_iter()
+            .collect()
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct InstanceJson {
     pub name: String,
     pub template: String,
@@ -170,4 +277,28 @@ pub struct InstanceJson {
     pub online_count: i32,
 
     pub ip: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub players: Option<Vec<String>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ServerInfoJson {
+    pub version: String,
+    pub protocol: i32,
+
+    pub motd: String,
+
+    pub max_players: i32,
+    pub online_count: i32,
+
+    pub players: Vec<String>,
 }
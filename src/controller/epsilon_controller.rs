@@ -3,12 +3,13 @@ use crate::controller::definitions::epsilon_instance::{
     EpsilonInstance, EpsilonInstanceSpec, EpsilonInstanceStatus,
 };
 use crate::controller::definitions::epsilon_queue::EpsilonQueue;
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
 use crate::epsilon::epsilon_error::EpsilonError;
 use crate::epsilon::server::instances::common::state::EpsilonState;
-use crate::TemplateProvider;
+use crate::{EpsilonApi, TemplateProvider};
 use futures::stream::StreamExt;
 use k8s_openapi::api::core::v1::{
-    ConfigMapEnvSource, Container, EnvFromSource, ExecAction, Pod, PodSpec, Probe,
+    ConfigMapEnvSource, Container, EnvFromSource, ExecAction, Pod, PodSpec, Probe, Secret,
 };
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
@@ -24,6 +25,8 @@ use std::env;
 use std::sync::Arc;
 use std::time::Duration;
 
+pub const ALLOCATED_ANNOTATION: &str = "epsilon.fr/allocated";
+
 pub struct EpsilonController {
     context: Arc<Context>,
     store: Arc<Store<EpsilonInstance>>,
@@ -33,19 +36,23 @@ impl EpsilonController {
     pub async fn new(
         namespace: &str,
         instance_provider: &Arc<TemplateProvider>,
+        epsilon_api: &Arc<EpsilonApi>,
     ) -> Arc<EpsilonController> {
         let config = Config::infer().await.expect("Failed to load kube config");
         let client = Client::try_from(config).expect("Failed to create kube client");
 
         let pod_api: Api<Pod> = Api::namespaced(client.clone(), namespace);
+        let secret_api: Api<Secret> = Api::namespaced(client.clone(), namespace);
 
         let epsilon_instance_api: Api<EpsilonInstance> = Api::namespaced(client.clone(), namespace);
         let epsilon_queue_api: Api<EpsilonQueue> = Api::namespaced(client.clone(), namespace);
 
         let context: Arc<Context> = Arc::new(Context::new(
             pod_api.clone(),
+            secret_api,
             epsilon_instance_api.clone(),
             instance_provider,
+            epsilon_api,
         ));
 
         let clone_context = Arc::clone(&context);
@@ -85,6 +92,7 @@ impl EpsilonController {
         let epsilon_instance_api = &context.epsilon_instance_api;
 
         let template_provider = &context.template_provider;
+        let epsilon_api = &context.epsilon_api;
 
         let instance_spec = &epsilon_instance.spec;
         let instance_status = epsilon_instance.status.clone();
@@ -199,6 +207,8 @@ impl EpsilonController {
                             EpsilonState::Stopping
                         };
 
+                        let old_state = instance_status.as_ref().map(|status| status.state);
+
                         let mut new_status = match instance_status {
                             None => {
                                 let template = template_provider
@@ -220,7 +230,11 @@ impl EpsilonController {
                                     slots: template.slots,
                                     online: 0,
 
+                                    version: None,
+                                    protocol: None,
+
                                     close: state == EpsilonState::Stopping,
+                                    allocated: epsilon_instance.is_allocated(),
 
                                     state,
                                 }
@@ -231,35 +245,88 @@ impl EpsilonController {
                                 status.state = state;
 
                                 if state == EpsilonState::Running {
-                                    status.online =
-                                        epsilon_instance.get_online_count().await.unwrap_or(0);
+                                    match epsilon_instance.get_server_info().await {
+                                        Ok(info) => {
+                                            status.online = info.online_count;
+                                            status.version = Some(info.version);
+                                            status.protocol = Some(info.protocol);
+                                        }
+                                        Err(_) => status.online = 0,
+                                    }
                                 }
 
                                 status
                             }
                         };
 
+                        let status_patch = if old_state.is_none() {
+                            json!({ "status": new_status })
+                        } else {
+                            Self::get_status_patch(&new_status)
+                        };
+
                         epsilon_instance_api
                             .patch_status(
                                 &instance_name,
                                 &PatchParams::default(),
-                                &Patch::Merge(json!({ "status": new_status })),
+                                &Patch::Merge(status_patch),
                             )
                             .await?;
 
                         // debug!("Patch status ({}) : {}", instance_name, json!({ "status": new_status }));
 
+                        if old_state != Some(new_status.state) {
+                            epsilon_api
+                                .send(EpsilonEvent::StateChanged {
+                                    instance: instance_name.clone(),
+                                    template: instance_template_name.to_owned(),
+                                    old: old_state,
+                                    new: new_status.state,
+                                })
+                                .ok();
+                        }
+
                         let state = &new_status.state;
                         let close = &new_status.close;
 
                         if *state == EpsilonState::Stopping && !*close {
                             new_status.close = true;
 
+                            if pod_phase == "Failed" {
+                                let reason = pod_status
+                                    .container_statuses
+                                    .as_ref()
+                                    .and_then(|statuses| statuses.first())
+                                    .and_then(|status| status.state.as_ref())
+                                    .and_then(|state| state.terminated.as_ref())
+                                    .map(|terminated| {
+                                        format!(
+                                            "{} (exit_code={})",
+                                            terminated.reason.as_deref().unwrap_or("Error"),
+                                            terminated.exit_code
+                                        )
+                                    });
+
+                                warn!(
+                                    "Instance {} crashed (reason={})",
+                                    instance_name,
+                                    reason.as_deref().unwrap_or("unknown")
+                                );
+
+                                epsilon_api
+                                    .send(EpsilonEvent::InstanceCrashed {
+                                        instance: instance_name.clone(),
+                                        template: instance_template_name.to_owned(),
+                                        reason,
+                                    })
+                                    .ok();
+                            }
+
                             epsilon_instance_api
                                 .patch_status(
                                     &instance_name,
                                     &PatchParams::default(),
-                                    &Patch::Merge(json!({ "status": new_status })),
+                                    &Patch::Merge(Self::get_status_patch(&new_status)),
                                 )
                                 .await?;
 
@@ -267,6 +334,12 @@ impl EpsilonController {
                                 .delete(&instance_name, &DeleteParams::default())
                                 .await?;
 
+                            epsilon_api
+                                .send(EpsilonEvent::InstanceRemoved {
+                                    instance: instance_name.clone(),
+                                })
+                                .ok();
+
                             debug!("Close instance {}", instance_name)
                         }
                     }
@@ -282,6 +355,17 @@ impl EpsilonController {
         Action::requeue(Duration::from_secs(5))
     }
 
+    // The allocated flag is only written by allocations, a stale status must not reset it
+    fn get_status_patch(status: &EpsilonInstanceStatus) -> Value {
+        let mut status = json!(status);
+
+        if let Some(fields) = status.as_object_mut() {
+            fields.remove("allocated");
+        }
+
+        json!({ "status": status })
+    }
+
     fn get_image(template: &str) -> String {
         format!(
             "{}/{}",
@@ -294,12 +378,22 @@ impl EpsilonController {
         &self,
         template_name: &str,
         content: Value,
+        labels: BTreeMap<String, String>,
+        allocated: bool,
     ) -> Result<EpsilonInstance, EpsilonError> {
         let epsilon_instance_api = &self.context.epsilon_instance_api;
 
+        let mut annotations = BTreeMap::new();
+
+        if allocated {
+            annotations.insert(ALLOCATED_ANNOTATION.to_owned(), true.to_string());
+        }
+
         let epsilon_instance = EpsilonInstance {
             metadata: ObjectMeta {
                 generate_name: Some(format!("{}-", template_name)),
+                labels: Some(labels).filter(|labels| !labels.is_empty()),
+                annotations: Some(annotations).filter(|annotations| !annotations.is_empty()),
                 ..Default::default()
             },
             spec: EpsilonInstanceSpec {
@@ -321,28 +415,66 @@ impl EpsilonController {
         let epsilon_instance_api = &self.context.epsilon_instance_api;
         let store = &self.store;
 
-        if let Some(epsilon_instance) = store.get(&ObjectRef::new(instance_name)) {
-            let mut instance_status = epsilon_instance
-                .status
-                .as_ref()
-                .ok_or(EpsilonError::RetrieveStatusError)?
-                .clone();
-
-            instance_status.state = EpsilonState::InGame;
-
-            epsilon_instance_api
-                .patch_status(
-                    instance_name,
-                    &PatchParams::default(),
-                    &Patch::Merge(json!({ "status": instance_status })),
-                )
-                .await
-                .map_err(|_| EpsilonError::RemoveInstanceError(instance_name.to_owned()))?;
-        }
+        let epsilon_instance = store
+            .get(&ObjectRef::new(instance_name))
+            .ok_or_else(|| EpsilonError::InstanceNotFoundError(instance_name.to_owned()))?;
+
+        let mut instance_status = epsilon_instance
+            .status
+            .as_ref()
+            .ok_or(EpsilonError::RetrieveStatusError)?
+            .clone();
+
+        instance_status.state = EpsilonState::InGame;
+
+        epsilon_instance_api
+            .patch_status(
+                instance_name,
+                &PatchParams::default(),
+                &Patch::Merge(Self::get_status_patch(&instance_status)),
+            )
+            .await?;
 
         Ok(())
     }
 
+    pub async fn allocate_epsilon_instance(
+        &self,
+        instance_name: &str,
+    ) -> Result<Option<EpsilonInstance>, EpsilonError> {
+        let epsilon_instance_api = &self.context.epsilon_instance_api;
+
+        let epsilon_instance = epsilon_instance_api.get(instance_name).await?;
+
+        let instance_status = epsilon_instance
+            .status
+            .as_ref()
+            .ok_or(EpsilonError::RetrieveStatusError)?;
+
+        if epsilon_instance.is_allocated() || instance_status.state != EpsilonState::Running {
+            return Ok(None);
+        }
+
+        // The resource version makes the claim fail with a conflict if the instance changed since
+        let patch = json!({
+            "metadata": { "resourceVersion": epsilon_instance.metadata.resource_version },
+            "status": { "allocated": true }
+        });
+
+        match epsilon_instance_api
+            .patch_status(instance_name, &PatchParams::default(), &Patch::Merge(patch))
+            .await
+        {
+            Ok(allocated_instance) => Ok(Some(allocated_instance)),
+            Err(kube::Error::Api(response)) if response.code == 409 => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub async fn get_secret(&self, secret_name: &str) -> Result<Secret, EpsilonError> {
+        Ok(self.context.secret_api.get(secret_name).await?)
+    }
+
     pub fn get_epsilon_instance_api(&self) -> Api<EpsilonInstance> {
         self.context.epsilon_instance_api.clone()
     }
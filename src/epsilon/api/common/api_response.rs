@@ -0,0 +1,18 @@
+use rocket::serde::json::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+
+pub type ApiResult<T> = Result<Json<ApiResponse<T>>, EpsilonError>;
+
+#[derive(Serialize, JsonSchema)]
+pub struct ApiResponse<T> {
+    pub data: T,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn json(data: T) -> Json<ApiResponse<T>> {
+        Json(Self { data })
+    }
+}
@@ -1,14 +1,159 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
 use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::queue::common::matchmaking::Team;
+use crate::epsilon::server::instances::common::state::EpsilonState;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub enum EpsilonEvent {
-    SendToServer(Group, String),
+    SendToServer {
+        group: Group,
+        server: String,
+        transfer: u64,
+    },
+
+    InstanceCreated {
+        instance: String,
+        template: String,
+    },
+    StateChanged {
+        instance: String,
+        template: String,
+        old: Option<EpsilonState>,
+        new: EpsilonState,
+    },
+    InstanceRemoved {
+        instance: String,
+    },
+    InstanceCrashed {
+        instance: String,
+        template: String,
+        reason: Option<String>,
+    },
+
+    QueueJoined {
+        queue: String,
+        group: Group,
+    },
+    QueueLeft {
+        queue: String,
+        players: Vec<String>,
+    },
+    QueueExpired {
+        queue: String,
+        group: Group,
+    },
+    QueuePosition {
+        queue: String,
+        group: Group,
+        position: usize,
+        players_ahead: usize,
+        estimated_wait: Option<u64>,
+    },
+    MatchFormed {
+        queue: String,
+        instance: String,
+        groups: Vec<Group>,
+
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        teams: Vec<Team>,
+    },
+
+    TemplateChanged {
+        template: String,
+    },
+
+    Gap {
+        from: u64,
+        to: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    pub id: u64,
+    pub event: EpsilonEvent,
+}
+
+impl EpsilonEvent {
+    pub const NAMES: &'static [&'static str] = &[
+        "SendToServer",
+        "InstanceCreated",
+        "StateChanged",
+        "InstanceRemoved",
+        "InstanceCrashed",
+        "QueueJoined",
+        "QueueLeft",
+        "QueueExpired",
+        "QueuePosition",
+        "MatchFormed",
+        "TemplateChanged",
+        "Gap",
+    ];
+
+    pub fn get_template(&self) -> Option<&str> {
+        match self {
+            EpsilonEvent::SendToServer { group, .. } => Some(&group.queue),
+            EpsilonEvent::InstanceCreated { template, .. }
+            | EpsilonEvent::StateChanged { template, .. }
+            | EpsilonEvent::InstanceCrashed { template, .. }
+            | EpsilonEvent::TemplateChanged { template } => Some(template),
+            EpsilonEvent::QueueJoined { queue, .. }
+            | EpsilonEvent::QueueLeft { queue, .. }
+            | EpsilonEvent::QueueExpired { queue, .. }
+            | EpsilonEvent::QueuePosition { queue, .. }
+            | EpsilonEvent::MatchFormed { queue, .. } => Some(queue),
+            EpsilonEvent::InstanceRemoved { .. } | EpsilonEvent::Gap { .. } => None,
+        }
+    }
+
+    pub fn get_instance(&self) -> Option<&str> {
+        match self {
+            EpsilonEvent::SendToServer { server, .. } => Some(server),
+            EpsilonEvent::InstanceCreated { instance, .. }
+            | EpsilonEvent::StateChanged { instance, .. }
+            | EpsilonEvent::InstanceRemoved { instance }
+            | EpsilonEvent::InstanceCrashed { instance, .. }
+            | EpsilonEvent::MatchFormed { instance, .. } => Some(instance),
+            _ => None,
+        }
+    }
+
+    pub fn get_proxy(&self) -> Option<&str> {
+        match self {
+            EpsilonEvent::SendToServer { group, .. }
+            | EpsilonEvent::QueueJoined { group, .. }
+            | EpsilonEvent::QueueExpired { group, .. }
+            | EpsilonEvent::QueuePosition { group, .. } => group.proxy.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn to_data(&self) -> Value {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(mut map)) => map.remove(&self.to_string()).unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
 }
 
 impl ToString for EpsilonEvent {
     fn to_string(&self) -> String {
         match self {
-            EpsilonEvent::SendToServer(_, _) => "SendToServer",
+            EpsilonEvent::SendToServer { .. } => "SendToServer",
+            EpsilonEvent::InstanceCreated { .. } => "InstanceCreated",
+            EpsilonEvent::StateChanged { .. } => "StateChanged",
+            EpsilonEvent::InstanceRemoved { .. } => "InstanceRemoved",
+            EpsilonEvent::InstanceCrashed { .. } => "InstanceCrashed",
+            EpsilonEvent::QueueJoined { .. } => "QueueJoined",
+            EpsilonEvent::QueueLeft { .. } => "QueueLeft",
+            EpsilonEvent::QueueExpired { .. } => "QueueExpired",
+            EpsilonEvent::QueuePosition { .. } => "QueuePosition",
+            EpsilonEvent::MatchFormed { .. } => "MatchFormed",
+            EpsilonEvent::TemplateChanged { .. } => "TemplateChanged",
+            EpsilonEvent::Gap { .. } => "Gap",
         }
         .to_owned()
     }
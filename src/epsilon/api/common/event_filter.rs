@@ -0,0 +1,58 @@
+use rocket::FromForm;
+use schemars::JsonSchema;
+
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::epsilon_error::EpsilonError;
+
+#[derive(Debug, Default, FromForm, JsonSchema)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Only send these event types
+    #[field(name = "type")]
+    #[serde(rename = "type")]
+    pub t: Vec<String>,
+
+    /// Only send events of this template, events without template are always sent
+    pub template: Option<String>,
+    /// Only send events of this instance, events without instance are always sent
+    pub instance: Option<String>,
+    /// Only send groups held by this proxy, groups without proxy are always sent
+    pub proxy: Option<String>,
+}
+
+impl EventFilter {
+    pub fn validate(&self) -> Result<(), EpsilonError> {
+        for event_type in &self.t {
+            if !EpsilonEvent::NAMES.contains(&event_type.as_str()) {
+                return Err(EpsilonError::InvalidRequestError(format!(
+                    "Event type {} does not exist",
+                    event_type
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // An event without the filtered attribute is never excluded by it
+    pub fn matches(&self, event: &EpsilonEvent) -> bool {
+        if let EpsilonEvent::Gap { .. } = event {
+            return true;
+        }
+
+        if !self.t.is_empty() && !self.t.contains(&event.to_string()) {
+            return false;
+        }
+
+        Self::match_attribute(&self.template, event.get_template())
+            && Self::match_attribute(&self.instance, event.get_instance())
+            && Self::match_attribute(&self.proxy, event.get_proxy())
+    }
+
+    fn match_attribute(expected: &Option<String>, value: Option<&str>) -> bool {
+        match (expected, value) {
+            (Some(expected), Some(value)) => expected == value,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+
+pub struct LastEventId(pub u64);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = EpsilonError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.headers().get_one("Last-Event-ID") {
+            Some(header) => match header.trim().parse::<u64>() {
+                Ok(id) => Outcome::Success(LastEventId(id)),
+                Err(_) => Outcome::Failure((
+                    Status::BadRequest,
+                    EpsilonError::InvalidRequestError(format!(
+                        "Last-Event-ID {} is not a valid event id",
+                        header
+                    )),
+                )),
+            },
+            None => Outcome::Forward(()),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt::Write;
+
+pub struct MetricsWriter {
+    buffer: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        Self {
+            buffer: String::new(),
+        }
+    }
+
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.write(name, help, "counter", &value.to_string())
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) -> &mut Self {
+        self.write(name, help, "gauge", &value.to_string())
+    }
+
+    // One sample per label value, under a single metric family
+    pub fn gauges(
+        &mut self,
+        name: &str,
+        help: &str,
+        label: &str,
+        samples: Vec<(String, f64)>,
+    ) -> &mut Self {
+        writeln!(self.buffer, "# HELP {} {}", name, help).ok();
+        writeln!(self.buffer, "# TYPE {} gauge", name).ok();
+
+        for (label_value, value) in samples {
+            writeln!(
+                self.buffer,
+                "{}{{{}=\"{}\"}} {}",
+                name,
+                label,
+                label_value.replace('\\', "\\\\").replace('"', "\\\""),
+                value
+            )
+            .ok();
+        }
+
+        self
+    }
+
+    pub fn finish(self) -> String {
+        self.buffer
+    }
+
+    fn write(&mut self, name: &str, help: &str, kind: &str, value: &str) -> &mut Self {
+        writeln!(self.buffer, "# HELP {} {}", name, help).ok();
+        writeln!(self.buffer, "# TYPE {} {}", name, kind).ok();
+        writeln!(self.buffer, "{} {}", name, value).ok();
+
+        self
+    }
+}
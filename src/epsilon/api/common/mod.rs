@@ -1 +1,6 @@
+pub mod api_response;
 pub mod epsilon_events;
+pub mod event_filter;
+pub mod last_event_id;
+pub mod metrics;
+pub mod rate_limiter;
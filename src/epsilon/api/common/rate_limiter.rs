@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::RateLimit;
+
+const MAX_BUCKETS: usize = 10_000;
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(10);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+// Token bucket per key, `requests` can be spent at once then they come back over `period`
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    map: HashMap<String, Bucket>,
+    cleaned_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> Self {
+        Self {
+            capacity: limit.requests as f64,
+            refill_per_second: limit.requests as f64 / limit.period.max(1) as f64,
+
+            buckets: Mutex::new(Buckets {
+                map: HashMap::new(),
+                cleaned_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Seconds to wait before the next request when the key is over its limit
+    pub fn check(&self, key: &str) -> Result<(), u64> {
+        self.check_all([key])
+            .map_err(|(_, retry_after)| retry_after)
+    }
+
+    // Spends a token of every key only when none of them is over its limit
+    pub fn check_all<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a str> + Clone,
+    ) -> Result<(), (&'a str, u64)> {
+        if self.capacity <= 0.0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        for key in keys.clone() {
+            let tokens = buckets
+                .map
+                .get(key)
+                .map_or(self.capacity, |bucket| self.refill(bucket, now));
+
+            if tokens < 1.0 {
+                return Err((key, ((1.0 - tokens) / self.refill_per_second).ceil() as u64));
+            }
+        }
+
+        if now.duration_since(buckets.cleaned_at) >= CLEANUP_INTERVAL {
+            self.clean(&mut buckets.map, now);
+            buckets.cleaned_at = now;
+        }
+
+        for key in keys {
+            let tokens = buckets
+                .map
+                .get(key)
+                .map_or(self.capacity, |bucket| self.refill(bucket, now));
+
+            if buckets.map.len() >= MAX_BUCKETS && !buckets.map.contains_key(key) {
+                Self::evict_oldest(&mut buckets.map);
+            }
+
+            buckets.map.insert(
+                key.to_owned(),
+                Bucket {
+                    tokens: tokens - 1.0,
+                    updated_at: now,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    // Full buckets hold no information, they are the same as a new one
+    fn clean(&self, buckets: &mut HashMap<String, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| self.refill(bucket, now) < self.capacity);
+    }
+
+    // Drops the tenth of the buckets updated the longest ago, so the next inserts do not scan again
+    fn evict_oldest(buckets: &mut HashMap<String, Bucket>) {
+        let mut updates: Vec<(Instant, String)> = buckets
+            .iter()
+            .map(|(key, bucket)| (bucket.updated_at, key.clone()))
+            .collect();
+
+        let count = (MAX_BUCKETS / 10).min(updates.len() - 1);
+        updates.select_nth_unstable_by_key(count, |(updated_at, _)| *updated_at);
+
+        for (_, key) in &updates[..count] {
+            buckets.remove(key);
+        }
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+
+        (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{RateLimiter, MAX_BUCKETS};
+    use crate::config::RateLimit;
+
+    #[test]
+    fn allows_burst_up_to_requests() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 3,
+            period: 3600,
+        });
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check("a"), Ok(()));
+        }
+
+        assert!(limiter.check("a").is_err());
+    }
+
+    #[test]
+    fn retry_after_is_time_for_one_token() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 2,
+            period: 10,
+        });
+
+        limiter.check("a").unwrap();
+        limiter.check("a").unwrap();
+
+        // One token comes back every 5 seconds
+        assert_eq!(limiter.check("a"), Err(5));
+    }
+
+    #[test]
+    fn keys_are_limited_separately() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 1,
+            period: 3600,
+        });
+
+        assert_eq!(limiter.check("a"), Ok(()));
+        assert!(limiter.check("a").is_err());
+
+        assert_eq!(limiter.check("b"), Ok(()));
+    }
+
+    #[test]
+    fn tokens_come_back_over_period() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 100,
+            period: 1,
+        });
+
+        for _ in 0..100 {
+            limiter.check("a").unwrap();
+        }
+
+        assert!(limiter.check("a").is_err());
+
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(limiter.check("a"), Ok(()));
+    }
+
+    #[test]
+    fn limited_key_spends_no_token_of_the_others() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 1,
+            period: 3600,
+        });
+
+        limiter.check("b").unwrap();
+
+        assert_eq!(limiter.check_all(["a", "b"]), Err(("b", 3600)));
+        assert_eq!(limiter.check("a"), Ok(()));
+    }
+
+    #[test]
+    fn buckets_stay_bounded() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 5,
+            period: 3600,
+        });
+
+        for index in 0..MAX_BUCKETS + 10 {
+            limiter.check(&index.to_string()).unwrap();
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+
+        assert!(buckets.map.len() <= MAX_BUCKETS);
+        assert!(buckets.map.contains_key(&(MAX_BUCKETS + 9).to_string()));
+    }
+
+    #[test]
+    fn zero_requests_is_unlimited() {
+        let limiter = RateLimiter::new(&RateLimit {
+            requests: 0,
+            period: 1,
+        });
+
+        for _ in 0..1000 {
+            assert_eq!(limiter.check("a"), Ok(()));
+        }
+    }
+}
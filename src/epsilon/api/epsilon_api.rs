@@ -1,30 +1,124 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 
-use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::api::common::epsilon_events::{EpsilonEvent, SequencedEvent};
 use crate::epsilon::epsilon_error::EpsilonError;
+use crate::EpsilonConfig;
 
 pub struct EpsilonApi {
-    channel: Sender<EpsilonEvent>,
+    channel: Sender<SequencedEvent>,
+
+    history: Mutex<EventHistory>,
+    replay_capacity: usize,
+}
+
+struct EventHistory {
+    last_id: u64,
+    events: VecDeque<SequencedEvent>,
+}
+
+pub struct EventReplay {
+    pub last_id: u64,
+    pub events: Vec<SequencedEvent>,
 }
 
 impl EpsilonApi {
-    pub fn new() -> EpsilonApi {
-        Self {
-            channel: channel::<EpsilonEvent>(1024).0,
-        }
+    pub fn new(config: &Arc<EpsilonConfig>) -> Arc<EpsilonApi> {
+        let replay_capacity = config.events.replay.max(1);
+
+        Arc::new(Self {
+            channel: channel::<SequencedEvent>(1024).0,
+
+            history: Mutex::new(EventHistory {
+                last_id: 0,
+                events: VecDeque::with_capacity(replay_capacity),
+            }),
+            replay_capacity,
+        })
     }
 
     pub fn send(&self, event: EpsilonEvent) -> Result<(), EpsilonError> {
         let event_name = event.to_string();
 
+        let mut history = self.history.lock().unwrap();
+
+        history.last_id += 1;
+
+        let sequenced_event = SequencedEvent {
+            id: history.last_id,
+            event,
+        };
+
+        if history.events.len() >= self.replay_capacity {
+            history.events.pop_front();
+        }
+
+        history.events.push_back(sequenced_event.clone());
+
         self.channel
-            .send(event)
+            .send(sequenced_event)
             .map_err(|_| EpsilonError::SendEventError(event_name))?;
 
         Ok(())
     }
 
-    pub fn subscribe(&self) -> Receiver<EpsilonEvent> {
-        self.channel.subscribe()
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> (EventReplay, Receiver<SequencedEvent>) {
+        let history = self.history.lock().unwrap();
+
+        let replay = match last_event_id {
+            Some(last_event_id) => Self::replay_from(&history, last_event_id),
+            None => EventReplay {
+                last_id: history.last_id,
+                events: Vec::new(),
+            },
+        };
+
+        (replay, self.channel.subscribe())
+    }
+
+    pub fn replay(&self, last_event_id: u64) -> EventReplay {
+        Self::replay_from(&self.history.lock().unwrap(), last_event_id)
+    }
+
+    fn replay_from(history: &EventHistory, last_event_id: u64) -> EventReplay {
+        // An id ahead of the sequence comes from a previous run, everything buffered is new
+        let last_event_id = if last_event_id > history.last_id {
+            0
+        } else {
+            last_event_id
+        };
+
+        let oldest_id = history
+            .events
+            .front()
+            .map(|sequenced_event| sequenced_event.id)
+            .unwrap_or(history.last_id + 1);
+
+        let mut events = Vec::new();
+
+        if last_event_id + 1 < oldest_id {
+            events.push(SequencedEvent {
+                id: oldest_id - 1,
+                event: EpsilonEvent::Gap {
+                    from: last_event_id + 1,
+                    to: oldest_id - 1,
+                },
+            });
+        }
+
+        events.extend(
+            history
+                .events
+                .iter()
+                .filter(|sequenced_event| sequenced_event.id > last_event_id)
+                .cloned(),
+        );
+
+        EventReplay {
+            last_id: history.last_id,
+            events,
+        }
     }
 }
@@ -1,4 +1,6 @@
 pub mod epsilon_api;
 
 pub mod common;
+pub mod openapi;
 pub mod routes;
+pub mod v2;
@@ -0,0 +1,217 @@
+use rocket::{Build, Rocket, Route};
+use rocket_okapi::okapi::openapi3::{
+    Components, Info, MediaType, Object, OpenApi, Operation, Parameter, ParameterValue, PathItem,
+    RefOr, Response, Responses, SecurityRequirement,
+};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::swagger_ui::{make_swagger_ui, SwaggerUIConfig};
+use schemars::gen::SchemaSettings;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::{JsonSchema, Map};
+
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::api::common::event_filter::EventFilter;
+
+pub struct EpsilonOpenApi {
+    spec: OpenApi,
+}
+
+impl EpsilonOpenApi {
+    pub fn new() -> Self {
+        Self {
+            spec: OpenApi {
+                openapi: String::from("3.0.0"),
+                info: Info {
+                    title: String::from("Epsilon"),
+                    version: env!("CARGO_PKG_VERSION").to_owned(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn mount(
+        &mut self,
+        rocket: Rocket<Build>,
+        base: &str,
+        (routes, spec): (Vec<Route>, OpenApi),
+    ) -> Rocket<Build> {
+        for (path, item) in spec.paths {
+            self.spec.paths.insert(Self::join_path(base, &path), item);
+        }
+
+        if let Some(components) = spec.components {
+            self.get_components().schemas.extend(components.schemas);
+        }
+
+        rocket.mount(base, routes)
+    }
+
+    pub fn mount_events(
+        &mut self,
+        rocket: Rocket<Build>,
+        base: &str,
+        routes: Vec<Route>,
+    ) -> Rocket<Build> {
+        let root_schema = SchemaSettings::openapi3()
+            .into_generator()
+            .into_root_schema_for::<EpsilonEvent>();
+
+        let components = self.get_components();
+
+        for (name, schema) in root_schema.definitions {
+            if let Schema::Object(schema_object) = schema {
+                components.schemas.insert(name, schema_object);
+            }
+        }
+
+        components
+            .schemas
+            .insert(String::from("EpsilonEvent"), root_schema.schema);
+
+        for route in &routes {
+            let path = Self::join_path(base, &route.uri.path().to_string());
+
+            self.spec.paths.insert(
+                path,
+                PathItem {
+                    get: Some(Self::event_stream_operation()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        rocket.mount(base, routes)
+    }
+
+    pub fn attach(self, rocket: Rocket<Build>, settings: &OpenApiSettings) -> Rocket<Build> {
+        rocket
+            .mount(
+                "/",
+                vec![rocket_okapi::get_openapi_route(self.spec, settings)],
+            )
+            .mount(
+                "/swagger-ui/",
+                make_swagger_ui(&SwaggerUIConfig {
+                    url: String::from("../openapi.json"),
+                    ..Default::default()
+                }),
+            )
+    }
+
+    fn get_components(&mut self) -> &mut Components {
+        self.spec.components.get_or_insert_with(Components::default)
+    }
+
+    fn event_stream_operation() -> Operation {
+        let mut content = Map::new();
+        content.insert(
+            String::from("text/event-stream"),
+            MediaType {
+                schema: Some(SchemaObject::new_ref(String::from(
+                    "#/components/schemas/EpsilonEvent",
+                ))),
+                ..Default::default()
+            },
+        );
+
+        let mut responses = Responses::default();
+        responses.responses.insert(
+            String::from("200"),
+            RefOr::Object(Response {
+                description: String::from(
+                    "Named server-sent events, with the event content as data and a sequence id",
+                ),
+                content,
+                ..Default::default()
+            }),
+        );
+
+        let mut parameters = vec![Self::parameter(
+            "Last-Event-ID",
+            "header",
+            "Resume after this event id, a Gap event is sent when events are no longer buffered",
+            SchemaObject {
+                instance_type: Some(InstanceType::Integer.into()),
+                format: Some(String::from("uint64")),
+                ..Default::default()
+            },
+        )];
+
+        parameters.extend(Self::query_parameters::<EventFilter>());
+
+        let mut security_requirement = SecurityRequirement::new();
+        security_requirement.insert(String::from("bearer"), Vec::new());
+
+        Operation {
+            tags: vec![String::from("Api")],
+            operation_id: Some(String::from("events")),
+            parameters,
+            responses,
+            security: Some(vec![security_requirement]),
+            ..Default::default()
+        }
+    }
+
+    fn query_parameters<T: JsonSchema>() -> Vec<RefOr<Parameter>> {
+        let mut settings = SchemaSettings::openapi3();
+        settings.inline_subschemas = true;
+
+        let root_schema = settings.into_generator().into_root_schema_for::<T>();
+
+        root_schema
+            .schema
+            .object
+            .map(|object| object.properties)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, schema)| match schema {
+                Schema::Object(mut schema) => {
+                    let description = schema
+                        .metadata
+                        .as_mut()
+                        .and_then(|metadata| metadata.description.take())
+                        .unwrap_or_default();
+
+                    Some(Self::parameter(&name, "query", &description, schema))
+                }
+                Schema::Bool(_) => None,
+            })
+            .collect()
+    }
+
+    fn parameter(
+        name: &str,
+        location: &str,
+        description: &str,
+        schema: SchemaObject,
+    ) -> RefOr<Parameter> {
+        RefOr::Object(Parameter {
+            name: name.to_owned(),
+            location: location.to_owned(),
+            description: Some(description.to_owned()),
+            required: false,
+            deprecated: false,
+            allow_empty_value: false,
+            value: ParameterValue::Schema {
+                style: None,
+                explode: None,
+                allow_reserved: false,
+                schema,
+                example: None,
+                examples: None,
+            },
+            extensions: Object::default(),
+        })
+    }
+
+    fn join_path(base: &str, path: &str) -> String {
+        let joined = format!("{}{}", base.trim_end_matches('/'), path);
+
+        match joined.trim_end_matches('/') {
+            "" => String::from("/"),
+            trimmed => trimmed.to_owned(),
+        }
+    }
+}
@@ -1,51 +1,120 @@
 use std::sync::Arc;
 
+use rocket::response::content::RawText;
 use rocket::response::stream::{Event, EventStream};
 use rocket::{Shutdown, State};
-use serde_json::json;
 use std::time::Duration;
 use tokio::select;
 use tokio::sync::broadcast::error::RecvError;
 
-use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::api::common::epsilon_events::{EpsilonEvent, SequencedEvent};
+use crate::epsilon::api::common::event_filter::EventFilter;
+use crate::epsilon::api::common::last_event_id::LastEventId;
+use crate::epsilon::api::common::metrics::MetricsWriter;
+use crate::epsilon::auth::guards::ReadAccess;
+use crate::epsilon::epsilon_error::EpsilonError;
 use crate::Context;
 
+#[rocket_okapi::openapi(tag = "Api")]
 #[rocket::get("/ping")]
 pub async fn ping() -> &'static str {
     "Pong"
 }
 
-#[rocket::get("/events")]
-pub async fn events(context: &State<Arc<Context>>, mut end: Shutdown) -> EventStream![] {
-    let epsilon_api = context.get_epsilon_api();
+#[rocket_okapi::openapi(tag = "Api")]
+#[rocket::get("/metrics")]
+pub async fn metrics(_auth: ReadAccess, context: &State<Arc<Context>>) -> RawText<String> {
+    let mut writer = MetricsWriter::new();
 
-    let mut rx = epsilon_api.subscribe();
+    context
+        .get_transfer_provider()
+        .write_metrics(&mut writer)
+        .await;
+
+    context
+        .get_queue_provider()
+        .write_metrics(&mut writer)
+        .await;
+
+    RawText(writer.finish())
+}
+
+#[rocket_okapi::openapi(skip)]
+#[rocket::get("/events?<filter..>")]
+pub async fn events(
+    filter: EventFilter,
+    _auth: ReadAccess,
+    last_event_id: Option<LastEventId>,
+    context: &State<Arc<Context>>,
+    mut end: Shutdown,
+) -> Result<EventStream![], EpsilonError> {
+    filter.validate()?;
+
+    let epsilon_api = Arc::clone(context.get_epsilon_api());
+
+    let (replay, mut rx) =
+        epsilon_api.subscribe(last_event_id.map(|last_event_id| last_event_id.0));
 
     let stream = EventStream! {
+        let mut last_id = replay.last_id;
+
+        for sequenced_event in replay.events {
+            if filter.matches(&sequenced_event.event) {
+                yield to_sse(&sequenced_event);
+            }
+        }
+
         loop {
-            let event: EpsilonEvent = select! {
-                event = rx.recv() => match event {
-                    Ok(event) => event,
-                    Err(RecvError::Closed) => break,
-                    Err(RecvError::Lagged(_)) => continue,
-                },
+            let received = select! {
+                event = rx.recv() => event,
                 _ = &mut end => break,
             };
 
-            match &event {
-                EpsilonEvent::SendToServer(group, server) => {
-                    info!("Send to server {:?} [{}]", group, server);
+            let sequenced_event: SequencedEvent = match received {
+                Ok(event) => event,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Event stream lagged behind (skipped={})", skipped);
+
+                    for sequenced_event in epsilon_api.replay(last_id).events {
+                        if sequenced_event.id > last_id {
+                            last_id = sequenced_event.id;
 
-                    let json = json!({
-                        "group": group,
-                        "server": server,
-                    });
+                            if filter.matches(&sequenced_event.event) {
+                                yield to_sse(&sequenced_event);
+                            }
+                        }
+                    }
 
-                    yield Event::data(json.to_string()).event(event.to_string());
+                    continue;
                 }
+            };
+
+            if sequenced_event.id <= last_id {
+                continue;
+            }
+
+            last_id = sequenced_event.id;
+
+            if !filter.matches(&sequenced_event.event) {
+                continue;
             }
+
+            if let EpsilonEvent::SendToServer { group, server, transfer } = &sequenced_event.event {
+                info!("Send to server {:?} [{}] (transfer={})", group, server, transfer);
+            }
+
+            yield to_sse(&sequenced_event);
         }
     };
 
-    stream.heartbeat(Duration::from_secs(5))
+    Ok(stream.heartbeat(Duration::from_secs(5)))
+}
+
+fn to_sse(sequenced_event: &SequencedEvent) -> Event {
+    let event = &sequenced_event.event;
+
+    Event::data(event.to_data().to_string())
+        .event(event.to_string())
+        .id(sequenced_event.id.to_string())
 }
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::controller::definitions::epsilon_instance::{InstanceJson, ServerInfoJson};
+use crate::epsilon::api::common::api_response::{ApiResponse, ApiResult};
+use crate::epsilon::auth::guards::{AdminAccess, ArrivalAccess, ReadAccess, ServerAccess};
+use crate::epsilon::server::instances::common::allocation::{AllocationJson, AllocationRequest};
+use crate::epsilon::server::instances::common::creation::{
+    CreateInstanceRequest, CreatedInstanceJson,
+};
+use crate::epsilon::server::instances::common::instance_query::{InstancePage, InstanceQuery};
+use crate::Context;
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::get("/?<query..>")]
+pub async fn list(
+    query: InstanceQuery,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<InstancePage> {
+    let instance_provider = context.get_instance_provider();
+
+    Ok(ApiResponse::json(
+        instance_provider.list_instances(&query).await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::get("/<name>")]
+pub async fn get(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<InstanceJson> {
+    let instance_provider = context.get_instance_provider();
+
+    let instance = instance_provider.get_instance(name).await?;
+
+    Ok(ApiResponse::json(instance.to_json().await?))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::get("/<name>/info")]
+pub async fn info(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<ServerInfoJson> {
+    let instance_provider = context.get_instance_provider();
+
+    let instance = instance_provider.get_instance(name).await?;
+
+    Ok(ApiResponse::json(instance.get_server_info().await?))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::post("/", format = "json", data = "<body>")]
+pub async fn create(
+    body: Json<CreateInstanceRequest>,
+    _auth: AdminAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<CreatedInstanceJson> {
+    let instance_provider = context.get_instance_provider();
+    let request = body.into_inner();
+
+    let instance = instance_provider
+        .start_instance(&request.template, Some(request.content))
+        .await?;
+
+    info!(
+        "An instance has been created (template={})",
+        request.template
+    );
+
+    Ok(ApiResponse::json(CreatedInstanceJson {
+        name: instance.get_name(),
+        template: request.template,
+    }))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::post("/allocate", format = "json", data = "<body>")]
+pub async fn allocate(
+    body: Json<AllocationRequest>,
+    _auth: AdminAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<AllocationJson> {
+    let instance_provider = context.get_instance_provider();
+
+    let instance = instance_provider.allocate_instance(&body).await?;
+
+    Ok(ApiResponse::json(instance.to_allocation_json()))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::delete("/<name>")]
+pub async fn close(name: &str, auth: ServerAccess, context: &State<Arc<Context>>) -> ApiResult<()> {
+    let instance_provider = context.get_instance_provider();
+
+    auth.check_instance(instance_provider, name).await?;
+    instance_provider.remove_instance(name).await?;
+
+    info!("An instance has been closed (instance={})", name);
+
+    Ok(ApiResponse::json(()))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::post("/<name>/in_game")]
+pub async fn in_game(
+    name: &str,
+    auth: ServerAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<()> {
+    let instance_provider = context.get_instance_provider();
+
+    auth.check_instance(instance_provider, name).await?;
+    instance_provider.enable_in_game_instance(name).await?;
+
+    info!("An instance is now in game (name={})", name);
+
+    Ok(ApiResponse::json(()))
+}
+
+#[rocket_okapi::openapi(tag = "Instances (v2)")]
+#[rocket::post("/<name>/confirm", format = "json", data = "<players>")]
+pub async fn confirm(
+    name: &str,
+    players: Json<Vec<String>>,
+    auth: ArrivalAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<()> {
+    let instance_provider = context.get_instance_provider();
+
+    auth.check_instance(instance_provider, name).await?;
+    instance_provider.release_slots(name, &players).await;
+
+    context
+        .get_transfer_provider()
+        .confirm_arrival(name, &players)
+        .await;
+
+    info!(
+        "Player {} arrived on instance (name={})",
+        players.join("/"),
+        name
+    );
+
+    Ok(ApiResponse::json(()))
+}
@@ -0,0 +1,4 @@
+pub mod instances;
+pub mod proxies;
+pub mod queues;
+pub mod templates;
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use rocket::State;
+
+use crate::controller::definitions::epsilon_instance::{InstanceJson, VectorOfInstance};
+use crate::epsilon::api::common::api_response::{ApiResponse, ApiResult};
+use crate::epsilon::auth::guards::ReadAccess;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::server::instances::common::instance_type::InstanceType;
+use crate::Context;
+
+#[rocket_okapi::openapi(tag = "Proxies (v2)")]
+#[rocket::get("/")]
+pub async fn list(
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<Vec<InstanceJson>> {
+    let instance_provider = context.get_instance_provider();
+
+    let proxies = instance_provider
+        .get_instances(Some(InstanceType::Proxy), None, None)
+        .await?;
+
+    Ok(ApiResponse::json(proxies.to_json().await?))
+}
+
+#[rocket_okapi::openapi(tag = "Proxies (v2)")]
+#[rocket::get("/<name>")]
+pub async fn get(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<InstanceJson> {
+    let instance_provider = context.get_instance_provider();
+
+    let proxy = instance_provider.get_instance(name).await?;
+
+    if proxy.status.as_ref().map(|status| status.t) != Some(InstanceType::Proxy) {
+        return Err(EpsilonError::InstanceNotFoundError(name.to_owned()));
+    }
+
+    Ok(ApiResponse::json(proxy.to_json().await?))
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::epsilon::api::common::api_response::{ApiResponse, ApiResult};
+use crate::epsilon::auth::guards::{ProxyAccess, ReadAccess};
+use crate::epsilon::queue::common::epsilon_queue::{
+    PlayerPositionJson, QueueDetailsJson, QueueJoinJson, QueueJson,
+};
+use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::queue::common::leave_request::LeaveRequest;
+use crate::epsilon::queue::common::offline_request::OfflineRequest;
+use crate::epsilon::queue::common::queue_statistics::QueueStatisticsJson;
+use crate::epsilon::queue::common::transfer::TransferAck;
+use crate::Context;
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::get("/")]
+pub async fn list(_auth: ReadAccess, context: &State<Arc<Context>>) -> ApiResult<Vec<QueueJson>> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(queue_provider.get_queues_json().await))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::get("/<name>")]
+pub async fn get(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<QueueJson> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider.get_queue_json(name).await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::get("/<name>/details", rank = 2)]
+pub async fn details(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<QueueDetailsJson> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider.get_queue_details_json(name).await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::get("/<name>/stats", rank = 2)]
+pub async fn stats(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<QueueStatisticsJson> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider.get_queue_statistics_json(name).await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::get("/players/<uuid>")]
+pub async fn player(
+    uuid: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<PlayerPositionJson> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider.get_player_position(uuid).await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::post("/push", format = "json", data = "<body>")]
+pub async fn push(
+    body: Json<Group>,
+    auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<QueueJoinJson> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider
+            .push(body.into_inner(), &auth.get_caller())
+            .await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::post("/leave", format = "json", data = "<body>")]
+pub async fn leave(
+    body: Json<LeaveRequest>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<Vec<String>> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(ApiResponse::json(
+        queue_provider
+            .leave(&body.player, body.queue.as_deref())
+            .await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::post("/offline", format = "json", data = "<body>")]
+pub async fn offline(
+    body: Json<OfflineRequest>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<Vec<String>> {
+    let queue_provider = context.get_queue_provider();
+
+    context
+        .get_transfer_provider()
+        .forget_players(&body.players, context.get_instance_provider())
+        .await;
+
+    Ok(ApiResponse::json(
+        queue_provider.remove_offline(&body.players).await,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queues (v2)")]
+#[rocket::post("/transfers/<id>/ack", format = "json", data = "<body>")]
+pub async fn acknowledge(
+    id: u64,
+    body: Json<TransferAck>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<()> {
+    context
+        .get_transfer_provider()
+        .acknowledge(
+            id,
+            &body,
+            context.get_queue_provider(),
+            context.get_instance_provider(),
+        )
+        .await?;
+
+    Ok(ApiResponse::json(()))
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use rocket::State;
+
+use crate::epsilon::api::common::api_response::{ApiResponse, ApiResult};
+use crate::epsilon::auth::guards::ReadAccess;
+use crate::epsilon::server::templates::template::Template;
+use crate::Context;
+
+#[rocket_okapi::openapi(tag = "Templates (v2)")]
+#[rocket::get("/")]
+pub async fn list(_auth: ReadAccess, context: &State<Arc<Context>>) -> ApiResult<Vec<Template>> {
+    let template_provider = context.get_template_provider();
+
+    Ok(ApiResponse::json(template_provider.get_templates().await?))
+}
+
+#[rocket_okapi::openapi(tag = "Templates (v2)")]
+#[rocket::get("/<name>")]
+pub async fn get(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> ApiResult<Template> {
+    let template_provider = context.get_template_provider();
+
+    Ok(ApiResponse::json(
+        template_provider.get_template(name).await?,
+    ))
+}
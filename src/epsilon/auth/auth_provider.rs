@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use crate::epsilon::auth::common::role::Role;
+use crate::epsilon::auth::common::token::Token;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::{EpsilonConfig, EpsilonController};
+
+const SECRET_KEY: &str = "tokens";
+
+pub struct AuthProvider {
+    enabled: bool,
+    tokens: HashMap<String, Arc<Token>>,
+    anonymous: Arc<Token>,
+}
+
+impl AuthProvider {
+    pub async fn new(
+        config: &Arc<EpsilonConfig>,
+        epsilon_controller: &Arc<EpsilonController>,
+    ) -> Result<AuthProvider, EpsilonError> {
+        let auth_config = &config.auth;
+        let mut tokens = Vec::new();
+
+        if let Some(file) = &auth_config.file {
+            let json = fs::read_to_string(file).map_err(|_| {
+                EpsilonError::ParseJsonError(format!("Failed to read token file {}", file))
+            })?;
+
+            tokens.extend(Self::parse_tokens(&json)?);
+        }
+
+        if let Some(secret_name) = &auth_config.secret {
+            let secret = epsilon_controller.get_secret(secret_name).await?;
+
+            let json = secret
+                .data
+                .as_ref()
+                .and_then(|data| data.get(SECRET_KEY))
+                .map(|bytes| String::from_utf8_lossy(&bytes.0).into_owned())
+                .ok_or_else(|| {
+                    EpsilonError::ParseJsonError(format!(
+                        "Secret {} has no {} key",
+                        secret_name, SECRET_KEY
+                    ))
+                })?;
+
+            tokens.extend(Self::parse_tokens(&json)?);
+        }
+
+        if auth_config.enabled && tokens.is_empty() {
+            return Err(EpsilonError::ConfigError(String::from(
+                "Authentication is enabled but no token is configured, set auth.file or auth.secret",
+            )));
+        }
+
+        if auth_config.enabled {
+            info!("Authentication is enabled with {} token(s)", tokens.len());
+        } else {
+            warn!("Authentication is disabled, every request is granted admin access");
+        }
+
+        Ok(AuthProvider {
+            enabled: auth_config.enabled,
+            tokens: tokens
+                .into_iter()
+                .map(|token| (token.token.to_owned(), Arc::new(token)))
+                .collect(),
+            anonymous: Arc::new(Token {
+                name: String::from("anonymous"),
+                token: String::new(),
+                role: Role::Admin,
+            }),
+        })
+    }
+
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<Arc<Token>, EpsilonError> {
+        if !self.enabled {
+            return Ok(Arc::clone(&self.anonymous));
+        }
+
+        let token = authorization
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(EpsilonError::UnauthorizedError)?;
+
+        self.tokens
+            .get(token.trim())
+            .map(Arc::clone)
+            .ok_or(EpsilonError::UnauthorizedError)
+    }
+
+    fn parse_tokens(json: &str) -> Result<Vec<Token>, EpsilonError> {
+        serde_json::from_str::<Vec<Token>>(json)
+            .map_err(|_| EpsilonError::ParseJsonError("Auth Tokens".to_owned()))
+    }
+}
@@ -0,0 +1,2 @@
+pub mod role;
+pub mod token;
@@ -0,0 +1,23 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    ReadOnly,
+    GameServer,
+    Proxy,
+    Admin,
+}
+
+impl ToString for Role {
+    fn to_string(&self) -> String {
+        match self {
+            Role::ReadOnly => "read_only",
+            Role::GameServer => "game_server",
+            Role::Proxy => "proxy",
+            Role::Admin => "admin",
+        }
+        .to_owned()
+    }
+}
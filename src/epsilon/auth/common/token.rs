@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use crate::epsilon::auth::common::role::Role;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
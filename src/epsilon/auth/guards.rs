@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{
+    Object, SecurityRequirement, SecurityScheme, SecuritySchemeData,
+};
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+
+use crate::epsilon::auth::common::role::Role;
+use crate::epsilon::auth::common::token::Token;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::{Context, InstanceProvider};
+
+pub trait Permission: Send + Sync + 'static {
+    const ROLES: &'static [Role];
+}
+
+pub struct ReadPermission;
+pub struct ServerPermission;
+pub struct ProxyPermission;
+pub struct ArrivalPermission;
+pub struct AdminPermission;
+
+impl Permission for ReadPermission {
+    const ROLES: &'static [Role] = &[Role::ReadOnly, Role::GameServer, Role::Proxy, Role::Admin];
+}
+
+impl Permission for ServerPermission {
+    const ROLES: &'static [Role] = &[Role::GameServer, Role::Admin];
+}
+
+impl Permission for ProxyPermission {
+    const ROLES: &'static [Role] = &[Role::Proxy, Role::Admin];
+}
+
+impl Permission for ArrivalPermission {
+    const ROLES: &'static [Role] = &[Role::GameServer, Role::Proxy, Role::Admin];
+}
+
+impl Permission for AdminPermission {
+    const ROLES: &'static [Role] = &[Role::Admin];
+}
+
+pub type ReadAccess = Auth<ReadPermission>;
+pub type ServerAccess = Auth<ServerPermission>;
+pub type ProxyAccess = Auth<ProxyPermission>;
+pub type ArrivalAccess = Auth<ArrivalPermission>;
+pub type AdminAccess = Auth<AdminPermission>;
+
+pub struct Auth<P: Permission> {
+    pub token: Arc<Token>,
+    pub ip: Option<IpAddr>,
+
+    permission: PhantomData<P>,
+}
+
+impl<P: Permission> Auth<P> {
+    // Tokens can be shared by several proxies, so callers are told apart by address too
+    pub fn get_caller(&self) -> String {
+        match self.ip {
+            Some(ip) => format!("{}/{}", self.token.name, ip),
+            None => self.token.name.to_owned(),
+        }
+    }
+
+    pub async fn check_instance(
+        &self,
+        instance_provider: &InstanceProvider,
+        instance_name: &str,
+    ) -> Result<(), EpsilonError> {
+        if self.token.role != Role::GameServer {
+            return Ok(());
+        }
+
+        let instance = instance_provider.get_instance(instance_name).await?;
+
+        let instance_ip = instance
+            .status
+            .as_ref()
+            .and_then(|status| status.ip.as_ref());
+
+        match (self.ip, instance_ip) {
+            (Some(ip), Some(instance_ip)) if ip.to_string() == *instance_ip => Ok(()),
+            _ => Err(EpsilonError::ForbiddenError(format!(
+                "Token {} can only modify its own instance",
+                self.token.name
+            ))),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, P: Permission> FromRequest<'r> for Auth<P> {
+    type Error = EpsilonError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let context = match request.rocket().state::<Arc<Context>>() {
+            Some(context) => context,
+            None => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    EpsilonError::ApiServerError("Context is not managed".to_owned()),
+                ))
+            }
+        };
+
+        let authorization = request.headers().get_one("Authorization");
+
+        match context.get_auth_provider().authenticate(authorization) {
+            Ok(token) if P::ROLES.contains(&token.role) => Outcome::Success(Auth {
+                token,
+                // X-Real-IP is set by the caller, only the connection address can be trusted
+                ip: request.remote().map(|remote| remote.ip()),
+
+                permission: PhantomData,
+            }),
+            Ok(token) => Outcome::Failure((
+                Status::Forbidden,
+                EpsilonError::ForbiddenError(format!(
+                    "Role {} is not allowed on {}",
+                    token.role.to_string(),
+                    request.uri()
+                )),
+            )),
+            Err(error) => Outcome::Failure((Status::Unauthorized, error)),
+        }
+    }
+}
+
+impl<'a, P: Permission> OpenApiFromRequest<'a> for Auth<P> {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let security_scheme = SecurityScheme {
+            description: Some(String::from(
+                "Bearer token, the roles allowed depend on the route",
+            )),
+            data: SecuritySchemeData::Http {
+                scheme: String::from("bearer"),
+                bearer_format: None,
+            },
+            extensions: Object::default(),
+        };
+
+        let mut security_requirement = SecurityRequirement::new();
+        security_requirement.insert(String::from("bearer"), Vec::new());
+
+        Ok(RequestHeaderInput::Security(
+            String::from("bearer"),
+            security_scheme,
+            security_requirement,
+        ))
+    }
+}
@@ -0,0 +1,4 @@
+pub mod auth_provider;
+
+pub mod common;
+pub mod guards;
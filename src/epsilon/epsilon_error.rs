@@ -1,6 +1,15 @@
+use std::error::Error as StdError;
+
 use rocket::http::Status;
 use rocket::response::Responder;
+use rocket::serde::json::Json;
 use rocket::{response, Request, Response};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::util::add_schema_response;
+use schemars::JsonSchema;
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -14,14 +23,20 @@ pub enum EpsilonError {
     #[error("Send event error {0}")]
     SendEventError(String),
 
+    #[error("Queue persistence error {0}")]
+    PersistenceError(String),
+
+    #[error("Invalid config {0}")]
+    ConfigError(String),
+
     #[error("Create instance error, template is {0}")]
     CreateInstanceError(String),
 
     #[error("Remove instance error {0}")]
-    RemoveInstanceError(String),
+    RemoveInstanceError(String, #[source] kube::Error),
 
     #[error("Retrieve instance error")]
-    RetrieveInstanceError,
+    RetrieveInstanceError(#[source] Option<kube::Error>),
 
     #[error("Retrieve status error")]
     RetrieveStatusError,
@@ -29,9 +44,33 @@ pub enum EpsilonError {
     #[error("Retrieve Ip address error")]
     RetrieveIpAddressError,
 
+    #[error("Instance not found error {0}")]
+    InstanceNotFoundError(String),
+
     #[error("Queue not found error {0}")]
     QueueNotFoundError(String),
 
+    #[error("Transfer not found error {0}")]
+    TransferNotFoundError(u64),
+
+    #[error("Player not found in any queue {0}")]
+    PlayerNotFoundError(String),
+
+    #[error("Invalid request error {0}")]
+    InvalidRequestError(String),
+
+    #[error("Queue is full {0}")]
+    QueueFullError(String),
+
+    #[error("Too many requests from {0}, retry in {1} seconds")]
+    RateLimitedError(String, u64),
+
+    #[error("Unauthorized error")]
+    UnauthorizedError,
+
+    #[error("Forbidden error {0}")]
+    ForbiddenError(String),
+
     #[error("Kubernetes error {0}")]
     KubernetesError(#[from] kube::Error),
 
@@ -45,8 +84,148 @@ pub enum EpsilonError {
     TimeoutError(#[from] tokio::time::error::Elapsed),
 }
 
+#[derive(Serialize, JsonSchema)]
+pub struct ErrorJson {
+    pub code: String,
+    pub message: String,
+
+    pub causes: Vec<String>,
+}
+
+impl EpsilonError {
+    pub fn get_status(&self) -> Status {
+        match self {
+            EpsilonError::InstanceNotFoundError(_)
+            | EpsilonError::QueueNotFoundError(_)
+            | EpsilonError::TransferNotFoundError(_)
+            | EpsilonError::PlayerNotFoundError(_) => Status::NotFound,
+
+            EpsilonError::InvalidRequestError(_) => Status::BadRequest,
+            EpsilonError::QueueFullError(_) => Status::Conflict,
+            EpsilonError::RateLimitedError(_, _) => Status::TooManyRequests,
+
+            EpsilonError::UnauthorizedError => Status::Unauthorized,
+            EpsilonError::ForbiddenError(_) => Status::Forbidden,
+
+            EpsilonError::RetrieveStatusError | EpsilonError::RetrieveIpAddressError => {
+                Status::Conflict
+            }
+
+            EpsilonError::KubernetesError(kube::Error::Api(response)) => match response.code {
+                400 | 422 => Status::BadRequest,
+                404 => Status::NotFound,
+                409 => Status::Conflict,
+                _ => Status::ServiceUnavailable,
+            },
+
+            EpsilonError::SendEventError(_)
+            | EpsilonError::CreateInstanceError(_)
+            | EpsilonError::RemoveInstanceError(_, _)
+            | EpsilonError::RetrieveInstanceError(_)
+            | EpsilonError::KubernetesError(_)
+            | EpsilonError::RequestError(_)
+            | EpsilonError::PingMinecraftError(_)
+            | EpsilonError::TimeoutError(_) => Status::ServiceUnavailable,
+
+            EpsilonError::ApiServerError(_)
+            | EpsilonError::ParseJsonError(_)
+            | EpsilonError::PersistenceError(_)
+            | EpsilonError::ConfigError(_) => Status::InternalServerError,
+        }
+    }
+
+    pub fn get_code(&self) -> &'static str {
+        match self {
+            EpsilonError::ApiServerError(_) => "api_server_error",
+            EpsilonError::ParseJsonError(_) => "parse_json_error",
+            EpsilonError::SendEventError(_) => "send_event_error",
+            EpsilonError::PersistenceError(_) => "persistence_error",
+            EpsilonError::ConfigError(_) => "config_error",
+            EpsilonError::CreateInstanceError(_) => "create_instance_error",
+            EpsilonError::RemoveInstanceError(_, _) => "remove_instance_error",
+            EpsilonError::RetrieveInstanceError(_) => "retrieve_instance_error",
+            EpsilonError::RetrieveStatusError => "retrieve_status_error",
+            EpsilonError::RetrieveIpAddressError => "retrieve_ip_address_error",
+            EpsilonError::InstanceNotFoundError(_) => "instance_not_found",
+            EpsilonError::QueueNotFoundError(_) => "queue_not_found",
+            EpsilonError::TransferNotFoundError(_) => "transfer_not_found",
+            EpsilonError::PlayerNotFoundError(_) => "player_not_found",
+            EpsilonError::InvalidRequestError(_) => "invalid_request",
+            EpsilonError::QueueFullError(_) => "queue_full",
+            EpsilonError::RateLimitedError(_, _) => "rate_limited",
+            EpsilonError::UnauthorizedError => "unauthorized",
+            EpsilonError::ForbiddenError(_) => "forbidden",
+            EpsilonError::KubernetesError(_) => "kubernetes_error",
+            EpsilonError::RequestError(_) => "request_error",
+            EpsilonError::PingMinecraftError(_) => "ping_minecraft_error",
+            EpsilonError::TimeoutError(_) => "timeout_error",
+        }
+    }
+
+    pub fn to_json(&self) -> ErrorJson {
+        let mut causes = Vec::new();
+        let mut source = self.source();
+
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+
+        ErrorJson {
+            code: self.get_code().to_owned(),
+            message: self.to_string(),
+
+            causes,
+        }
+    }
+}
+
 impl<'r> Responder<'r, 'static> for EpsilonError {
-    fn respond_to(self, _req: &'r Request<'_>) -> response::Result<'static> {
-        Response::build().status(Status::InternalServerError).ok()
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.get_status();
+
+        if status.code >= 500 {
+            warn!("Request {} failed: {}", req.uri(), self);
+        }
+
+        let retry_after = match &self {
+            EpsilonError::RateLimitedError(_, retry_after) => Some(*retry_after),
+            _ => None,
+        };
+
+        let mut response = Response::build_from(Json(self.to_json()).respond_to(req)?);
+        response.status(status);
+
+        if let Some(retry_after) = retry_after {
+            response.raw_header("Retry-After", retry_after.to_string());
+        }
+
+        response.ok()
+    }
+}
+
+impl OpenApiResponderInner for EpsilonError {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        let schema = gen.json_schema::<ErrorJson>();
+
+        for status in [400, 401, 403, 404, 409, 429, 500, 503] {
+            add_schema_response(&mut responses, status, "application/json", schema.clone())?;
+        }
+
+        Ok(responses)
     }
 }
+
+#[rocket::catch(default)]
+pub fn default_catcher(status: Status, _req: &Request) -> Json<ErrorJson> {
+    Json(ErrorJson {
+        code: status
+            .reason_lossy()
+            .to_lowercase()
+            .replace(|c: char| !c.is_alphanumeric(), "_"),
+        message: format!("{} {}", status.code, status.reason_lossy()),
+
+        causes: Vec::new(),
+    })
+}
@@ -1,4 +1,6 @@
 pub mod api;
+pub mod auth;
 pub mod epsilon_error;
 pub mod queue;
 pub mod server;
+pub mod webhook;
@@ -1,35 +1,418 @@
+use std::cmp::Ordering;
 use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
 
-use crate::epsilon::queue::common::group::Group;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::config::PriorityPolicy;
+use crate::epsilon::queue::common::group::{Group, JoinTarget};
+use crate::epsilon::queue::common::queue_statistics::{QueueStatistics, QueueStatisticsJson};
+use crate::epsilon::queue::common::template_selector::TemplateSelector;
+use crate::epsilon::queue::store::queue_store::{get_unix_millis, StoredEntry};
+use crate::epsilon::server::templates::match_settings::MatchSettings;
+use crate::epsilon::server::templates::template::Template;
+
+pub struct QueueEntry {
+    pub group: Group,
+    pub priority: i32,
+    pub queued_at: Instant,
+
+    // Requeued groups already waited their turn and are served before anyone else
+    pub requeued: bool,
+    pub skipped: u32,
+}
+
+impl QueueEntry {
+    pub fn get_score(&self, policy: &PriorityPolicy, now: Instant) -> f64 {
+        if !policy.enabled {
+            return 0.0;
+        }
+
+        let aging = if policy.aging > 0 {
+            now.duration_since(self.queued_at).as_secs_f64() / policy.aging as f64
+        } else {
+            0.0
+        };
+
+        self.priority as f64 + aging
+    }
+
+    pub fn to_stored(&self) -> StoredEntry {
+        let waited = self.queued_at.elapsed().as_millis() as u64;
+
+        StoredEntry {
+            group: self.group.clone(),
+            priority: self.priority,
+            queued_at: get_unix_millis().saturating_sub(waited),
+
+            requeued: self.requeued,
+            skipped: self.skipped,
+        }
+    }
+
+    pub fn from_stored(entry: StoredEntry) -> Self {
+        let now = Instant::now();
+        let waited = entry.get_waited();
+
+        Self {
+            group: entry.group,
+            priority: entry.priority,
+            queued_at: now.checked_sub(waited).unwrap_or(now),
+
+            requeued: entry.requeued,
+            skipped: entry.skipped,
+        }
+    }
+}
 
 pub struct Queue {
-    queue: VecDeque<Group>,
-    in_queue: HashSet<String>,
+    queue: VecDeque<QueueEntry>,
+    templates: TemplateSelector,
+    policy: PriorityPolicy,
+    max_group_size: usize,
+    match_settings: Option<MatchSettings>,
+    max_wait: Option<Duration>,
+    max_length: Option<usize>,
+
+    statistics: QueueStatistics,
 }
 
 impl Queue {
-    pub fn new() -> Self {
+    // Groups must fit in any of the templates, match settings come from the first one
+    pub fn new(
+        templates: &[(&Template, u32)],
+        policy: PriorityPolicy,
+        max_wait: Option<Duration>,
+        max_length: Option<usize>,
+    ) -> Self {
         Self {
             queue: VecDeque::new(),
-            in_queue: HashSet::new(),
+            templates: TemplateSelector::new(
+                templates
+                    .iter()
+                    .map(|(template, weight)| (template.name.to_owned(), *weight))
+                    .collect(),
+            ),
+            policy,
+            max_group_size: templates
+                .iter()
+                .map(|(template, _)| template.slots.max(0) as usize)
+                .min()
+                .unwrap_or(0),
+            match_settings: templates
+                .first()
+                .and_then(|(template, _)| template.match_settings.clone()),
+            max_wait,
+            max_length,
+
+            statistics: QueueStatistics::new(),
+        }
+    }
+
+    pub fn push(&mut self, group: Group, priority: i32) -> &QueueEntry {
+        let priority = priority.clamp(0, self.policy.max_priority.max(0));
+
+        self.statistics.record_join(group.players.len());
+
+        self.queue.push_back(QueueEntry {
+            group,
+            priority,
+            queued_at: Instant::now(),
+
+            requeued: false,
+            skipped: 0,
+        });
+
+        &self.queue[self.queue.len() - 1]
+    }
+
+    pub fn requeue(&mut self, group: Group) -> &QueueEntry {
+        self.queue.push_front(QueueEntry {
+            group,
+            priority: 0,
+            queued_at: Instant::now(),
+
+            requeued: true,
+            skipped: 0,
+        });
+
+        &self.queue[0]
+    }
+
+    // Entries keep their arrival order, the queue order is computed from it
+    pub fn restore(&mut self, entries: Vec<StoredEntry>) {
+        self.queue
+            .extend(entries.into_iter().map(QueueEntry::from_stored));
+    }
+
+    pub fn to_stored(&self) -> Vec<StoredEntry> {
+        self.queue.iter().map(QueueEntry::to_stored).collect()
+    }
+
+    pub fn get_ordered(&self) -> Vec<&QueueEntry> {
+        self.get_order()
+            .into_iter()
+            .map(|index| &self.queue[index])
+            .collect()
+    }
+
+    fn get_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+
+        let scores: Vec<f64> = self
+            .queue
+            .iter()
+            .map(|entry| entry.get_score(&self.policy, now))
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.queue.len()).collect();
+
+        // The sort is stable, so equal scores keep their arrival order
+        order.sort_by(|a, b| {
+            self.queue[*b]
+                .requeued
+                .cmp(&self.queue[*a].requeued)
+                .then_with(|| {
+                    scores[*b]
+                        .partial_cmp(&scores[*a])
+                        .unwrap_or(Ordering::Equal)
+                })
+        });
+
+        order
+    }
+
+    // Takes every group `place` accepts in queue order. A group that does not fit can be
+    // overtaken by smaller ones at most `max_skips` times, then it blocks the queue until placed
+    pub fn pop_placeable<T, F>(&mut self, max_skips: u32, place: F) -> Vec<(Group, T)>
+    where
+        F: FnMut(&Group) -> Option<T>,
+    {
+        let (placed, overtaken) = self.select(max_skips, place);
+
+        for index in overtaken {
+            self.queue[index].skipped += 1;
+        }
+
+        self.take(placed)
+    }
+
+    // Same selection as `pop_placeable`, but nothing leaves the queue unless the selected
+    // groups hold at least `min_players` players
+    pub fn pop_roster<F>(&mut self, max_skips: u32, min_players: usize, mut accept: F) -> Vec<Group>
+    where
+        F: FnMut(&Group) -> bool,
+    {
+        let (selected, overtaken) = self.select(max_skips, |group| accept(group).then_some(()));
+
+        let players: usize = selected
+            .iter()
+            .map(|(index, _)| self.queue[*index].group.players.len())
+            .sum();
+
+        if selected.is_empty() || players < min_players {
+            return Vec::new();
+        }
+
+        for index in overtaken {
+            self.queue[index].skipped += 1;
+        }
+
+        self.take(selected)
+            .into_iter()
+            .map(|(group, _)| group)
+            .collect()
+    }
+
+    fn select<T, F>(&self, max_skips: u32, mut place: F) -> (Vec<(usize, T)>, HashSet<usize>)
+    where
+        F: FnMut(&Group) -> Option<T>,
+    {
+        let mut placed = Vec::new();
+        let mut waiting = Vec::new();
+        let mut overtaken = HashSet::new();
+
+        for index in self.get_order() {
+            let entry = &self.queue[index];
+
+            match place(&entry.group) {
+                Some(target) => {
+                    placed.push((index, target));
+                    overtaken.extend(waiting.drain(..));
+                }
+                // Targeted groups wait for their instance without holding back the queue
+                None if entry.group.target.is_some() => {}
+                None => {
+                    if entry.skipped >= max_skips {
+                        break;
+                    }
+
+                    waiting.push(index);
+                }
+            }
+        }
+
+        (placed, overtaken)
+    }
+
+    fn take<T>(&mut self, mut placed: Vec<(usize, T)>) -> Vec<(Group, T)> {
+        placed.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let mut entries: Vec<(QueueEntry, T)> = placed
+            .into_iter()
+            .filter_map(|(index, target)| Some((self.queue.remove(index)?, target)))
+            .collect();
+
+        entries.reverse();
+
+        for (entry, _) in &entries {
+            self.statistics
+                .record_dispatch(entry.group.players.len(), entry.queued_at.elapsed());
         }
+
+        entries
+            .into_iter()
+            .map(|(entry, target)| (entry.group, target))
+            .collect()
+    }
+
+    pub fn get_templates(&self) -> Vec<&str> {
+        self.templates.get_templates()
+    }
+
+    pub fn next_template(&mut self) -> Option<String> {
+        self.templates.next()
+    }
+
+    pub fn get_max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    pub fn get_max_group_size(&self) -> usize {
+        self.max_group_size
+    }
+
+    pub fn get_match_settings(&self) -> Option<&MatchSettings> {
+        self.match_settings.as_ref()
+    }
+
+    pub fn get_longest_wait(&self) -> Option<Duration> {
+        let now = Instant::now();
+
+        self.queue
+            .iter()
+            .map(|entry| now.duration_since(entry.queued_at))
+            .max()
+    }
+
+    pub fn expire(&mut self) -> Vec<Group> {
+        let max_wait = match self.max_wait {
+            Some(max_wait) => max_wait,
+            None => return Vec::new(),
+        };
+
+        let now = Instant::now();
+
+        let (expired, kept): (Vec<QueueEntry>, VecDeque<QueueEntry>) = self
+            .queue
+            .drain(..)
+            .partition(|entry| now.duration_since(entry.queued_at) >= max_wait);
+
+        self.queue = kept;
+
+        self.statistics
+            .record_abandon(expired.iter().map(|entry| entry.group.players.len()).sum());
+
+        expired.into_iter().map(|entry| entry.group).collect()
     }
 
-    pub fn push(&mut self, group: Group) {
-        for player in &group.players {
-            if self.in_queue.contains(player) {
-                self.queue
-                    .retain(|queue_group| !queue_group.players.contains(player));
+    pub fn get_targets(&self) -> HashSet<JoinTarget> {
+        self.queue
+            .iter()
+            .filter_map(|entry| entry.group.target.clone())
+            .collect()
+    }
+
+    // Groups still waiting for their target after `timeout` are placed like any other group
+    pub fn drop_targets(&mut self, timeout: Duration) -> Vec<Group> {
+        let now = Instant::now();
+
+        self.queue
+            .iter_mut()
+            .filter(|entry| {
+                entry.group.target.is_some() && now.duration_since(entry.queued_at) >= timeout
+            })
+            .map(|entry| {
+                let group = entry.group.clone();
+                entry.group.target = None;
+
+                group
+            })
+            .collect()
+    }
+
+    pub fn leave(&mut self, player: &str) -> bool {
+        let index = match self
+            .queue
+            .iter()
+            .position(|entry| entry.group.players.iter().any(|queued| queued == player))
+        {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let group = &mut self.queue[index].group;
+        group.players.retain(|queued| queued != player);
+
+        self.statistics.record_abandon(1);
+
+        if group.players.is_empty() {
+            self.queue.remove(index);
+        }
+
+        true
+    }
+
+    pub fn get_position(&self, player: &str) -> Option<(usize, usize, i32)> {
+        let mut players_ahead = 0;
+
+        for (index, entry) in self.get_ordered().into_iter().enumerate() {
+            if entry.group.players.iter().any(|queued| queued == player) {
+                return Some((index + 1, players_ahead, entry.priority));
             }
 
-            self.in_queue.insert(player.into());
+            players_ahead += entry.group.players.len();
         }
 
-        self.queue.push_back(group);
+        None
     }
 
-    pub fn pop(&mut self) -> Option<Group> {
-        self.queue.pop_front()
+    pub fn get_estimated_wait(&self, players_ahead: usize) -> Option<u64> {
+        self.statistics.get_estimated_wait(players_ahead)
+    }
+
+    // Every group with its position and the number of players ahead of it
+    pub fn get_positions(&self) -> Vec<(&Group, usize, usize)> {
+        let mut players_ahead = 0;
+
+        self.get_ordered()
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let position = (&entry.group, index + 1, players_ahead);
+                players_ahead += entry.group.players.len();
+
+                position
+            })
+            .collect()
+    }
+
+    pub fn get_player_count(&self) -> usize {
+        self.queue
+            .iter()
+            .map(|entry| entry.group.players.len())
+            .sum()
     }
 
     pub fn len(&self) -> usize {
@@ -39,4 +422,75 @@ impl Queue {
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
+
+    pub fn to_json(&self, name: &str) -> QueueJson {
+        QueueJson {
+            name: name.to_owned(),
+            length: self.len(),
+        }
+    }
+
+    pub fn to_statistics_json(&self, name: &str) -> QueueStatisticsJson {
+        self.statistics.to_json(name, self.get_player_count())
+    }
+
+    pub fn to_details_json(&self, name: &str) -> QueueDetailsJson {
+        let players = self.get_player_count();
+
+        QueueDetailsJson {
+            name: name.to_owned(),
+            length: self.len(),
+            players,
+            templates: self
+                .get_templates()
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+
+            groups: self
+                .get_ordered()
+                .into_iter()
+                .map(|entry| entry.group.clone())
+                .collect(),
+            estimated_wait: self.get_estimated_wait(players),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueueJson {
+    pub name: String,
+    pub length: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueueDetailsJson {
+    pub name: String,
+    pub length: usize,
+    pub players: usize,
+    pub templates: Vec<String>,
+
+    pub groups: Vec<Group>,
+    pub estimated_wait: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueueJoinJson {
+    pub queue: String,
+    pub position: usize,
+    pub players_ahead: usize,
+    pub priority: i32,
+
+    pub estimated_wait: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PlayerPositionJson {
+    pub player: String,
+    pub queue: String,
+    pub position: usize,
+    pub players_ahead: usize,
+    pub priority: i32,
+
+    pub estimated_wait: Option<u64>,
 }
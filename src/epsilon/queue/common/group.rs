@@ -1,7 +1,124 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Group {
     pub players: Vec<String>,
     pub queue: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<JoinTarget>,
+}
+
+// Minecraft names, or UUIDs with or without dashes
+pub fn is_valid_player(player: &str) -> bool {
+    let is_name = (3..=16).contains(&player.len())
+        && player
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_');
+
+    let digits: Vec<char> = player
+        .chars()
+        .filter(|character| *character != '-')
+        .collect();
+
+    let is_uuid = digits.len() == 32
+        && digits.iter().all(char::is_ascii_hexdigit)
+        && (player.len() == 32
+            || (player.len() == 36
+                && [8, 13, 18, 23]
+                    .iter()
+                    .all(|index| player.as_bytes()[*index] == b'-')));
+
+    is_name || is_uuid
+}
+
+#[cfg(test)]
+impl Group {
+    pub fn from_players(queue: &str, players: &[&str]) -> Self {
+        Self {
+            players: players.iter().map(|player| player.to_string()).collect(),
+            queue: queue.to_owned(),
+
+            proxy: None,
+
+            priority: None,
+            rank: None,
+
+            rating: None,
+
+            target: None,
+        }
+    }
+}
+
+// A group with a target only joins that instance, or the instance the player is on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinTarget {
+    Instance(String),
+    Player(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_player;
+
+    #[test]
+    fn accepts_minecraft_names() {
+        for player in ["abc", "Notch", "player_123", "abcdefghijklmnop"] {
+            assert!(is_valid_player(player), "{}", player);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for player in [
+            "",
+            "ab",
+            "abcdefghijklmnopq",
+            "bad-name",
+            "with space",
+            "émile",
+        ] {
+            assert!(!is_valid_player(player), "{}", player);
+        }
+    }
+
+    #[test]
+    fn accepts_uuids_with_or_without_dashes() {
+        for player in [
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "069a79f444e94726a5befca90e38aaf5",
+            "069A79F4-44E9-4726-A5BE-FCA90E38AAF5",
+        ] {
+            assert!(is_valid_player(player), "{}", player);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_uuids() {
+        for player in [
+            // Dashes in the wrong places
+            "069a79f444e9-4726-a5be-fca90e38-aaf5",
+            // Not hexadecimal
+            "069a79f4-44e9-4726-a5be-fca90e38aazz",
+            // One digit short
+            "069a79f4-44e9-4726-a5be-fca90e38aaf",
+            "069a79f444e94726a5befca90e38aa-f5",
+        ] {
+            assert!(!is_valid_player(player), "{}", player);
+        }
+    }
 }
@@ -0,0 +1,8 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LeaveRequest {
+    pub player: String,
+    pub queue: Option<String>,
+}
@@ -0,0 +1,302 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::server::templates::match_settings::RatingSettings;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Team {
+    pub players: Vec<String>,
+    pub rating: f64,
+}
+
+pub struct RatingWindow {
+    width: f64,
+    default_rating: f64,
+
+    min: f64,
+    max: f64,
+}
+
+impl RatingWindow {
+    pub fn new(settings: &RatingSettings, waited: Duration) -> Self {
+        let mut width = settings.window + settings.widening * waited.as_secs_f64();
+
+        if let Some(max_window) = settings.max_window {
+            width = width.min(max_window);
+        }
+
+        Self {
+            width: width.max(0.0),
+            default_rating: settings.default_rating,
+
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    // Accepts the group when the roster spread stays within the window
+    pub fn accept(&mut self, group: &Group) -> bool {
+        let rating = group.rating.unwrap_or(self.default_rating);
+
+        let min = self.min.min(rating);
+        let max = self.max.max(rating);
+
+        if max - min > self.width {
+            return false;
+        }
+
+        self.min = min;
+        self.max = max;
+
+        true
+    }
+}
+
+// Groups are never split, the strongest ones are placed first in the weakest team with room left
+pub fn balance_teams(groups: &[Group], team_count: usize, default_rating: f64) -> Vec<Team> {
+    let team_count = team_count.max(1);
+
+    let player_count: usize = groups.iter().map(|group| group.players.len()).sum();
+    let capacity = (player_count + team_count - 1) / team_count;
+
+    let get_total =
+        |group: &Group| group.rating.unwrap_or(default_rating) * group.players.len() as f64;
+
+    let mut sorted: Vec<&Group> = groups.iter().collect();
+    sorted.sort_by(|a, b| {
+        get_total(b)
+            .partial_cmp(&get_total(a))
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut teams = vec![
+        Team {
+            players: Vec::new(),
+            rating: 0.0,
+        };
+        team_count
+    ];
+
+    for group in sorted {
+        let index = teams
+            .iter()
+            .enumerate()
+            .filter(|(_, team)| team.players.len() + group.players.len() <= capacity)
+            .min_by(|(_, a), (_, b)| a.rating.partial_cmp(&b.rating).unwrap_or(Ordering::Equal))
+            .or_else(|| {
+                teams
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, team)| team.players.len())
+            })
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+
+        let team = &mut teams[index];
+        team.players.extend(group.players.iter().cloned());
+        team.rating += get_total(group);
+    }
+
+    teams
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{balance_teams, RatingWindow};
+    use crate::epsilon::queue::common::group::Group;
+    use crate::epsilon::server::templates::match_settings::RatingSettings;
+
+    #[test]
+    fn window_rejects_groups_beyond_the_spread() {
+        let mut window = RatingWindow::new(
+            &RatingSettings {
+                window: 100.0,
+                widening: 0.0,
+                max_window: None,
+
+                default_rating: 1000.0,
+            },
+            Duration::ZERO,
+        );
+
+        assert!(window.accept(&Group {
+            rating: Some(1000.0),
+            ..Group::from_players("ranked", &["a"])
+        }));
+        assert!(window.accept(&Group {
+            rating: Some(1080.0),
+            ..Group::from_players("ranked", &["b"])
+        }));
+        assert!(!window.accept(&Group {
+            rating: Some(1150.0),
+            ..Group::from_players("ranked", &["c"])
+        }));
+
+        // A rejected group does not move the bounds of the roster
+        assert!(window.accept(&Group {
+            rating: Some(1100.0),
+            ..Group::from_players("ranked", &["d"])
+        }));
+        assert!(!window.accept(&Group {
+            rating: Some(990.0),
+            ..Group::from_players("ranked", &["e"])
+        }));
+    }
+
+    #[test]
+    fn window_uses_the_default_rating() {
+        let mut window = RatingWindow::new(
+            &RatingSettings {
+                window: 50.0,
+                widening: 0.0,
+                max_window: None,
+
+                default_rating: 1000.0,
+            },
+            Duration::ZERO,
+        );
+
+        assert!(window.accept(&Group::from_players("ranked", &["a"])));
+        assert!(window.accept(&Group {
+            rating: Some(1040.0),
+            ..Group::from_players("ranked", &["b"])
+        }));
+        assert!(!window.accept(&Group {
+            rating: Some(1060.0),
+            ..Group::from_players("ranked", &["c"])
+        }));
+    }
+
+    #[test]
+    fn window_widens_with_the_wait() {
+        let settings = RatingSettings {
+            window: 100.0,
+            widening: 10.0,
+            max_window: None,
+
+            default_rating: 1000.0,
+        };
+
+        let mut window = RatingWindow::new(&settings, Duration::ZERO);
+        assert!(window.accept(&Group {
+            rating: Some(1000.0),
+            ..Group::from_players("ranked", &["a"])
+        }));
+        assert!(!window.accept(&Group {
+            rating: Some(1150.0),
+            ..Group::from_players("ranked", &["b"])
+        }));
+
+        let mut window = RatingWindow::new(&settings, Duration::from_secs(5));
+        assert!(window.accept(&Group {
+            rating: Some(1000.0),
+            ..Group::from_players("ranked", &["a"])
+        }));
+        assert!(window.accept(&Group {
+            rating: Some(1150.0),
+            ..Group::from_players("ranked", &["b"])
+        }));
+    }
+
+    #[test]
+    fn window_widening_stops_at_max_window() {
+        let settings = RatingSettings {
+            window: 100.0,
+            widening: 10.0,
+            max_window: Some(120.0),
+
+            default_rating: 1000.0,
+        };
+
+        let mut window = RatingWindow::new(&settings, Duration::from_secs(60));
+        assert!(window.accept(&Group {
+            rating: Some(1000.0),
+            ..Group::from_players("ranked", &["a"])
+        }));
+        assert!(window.accept(&Group {
+            rating: Some(1120.0),
+            ..Group::from_players("ranked", &["b"])
+        }));
+        assert!(!window.accept(&Group {
+            rating: Some(1121.0),
+            ..Group::from_players("ranked", &["c"])
+        }));
+    }
+
+    #[test]
+    fn teams_are_balanced_by_rating() {
+        let groups = vec![
+            Group {
+                rating: Some(1500.0),
+                ..Group::from_players("ranked", &["a"])
+            },
+            Group {
+                rating: Some(1000.0),
+                ..Group::from_players("ranked", &["b"])
+            },
+            Group {
+                rating: Some(1400.0),
+                ..Group::from_players("ranked", &["c"])
+            },
+            Group {
+                rating: Some(1100.0),
+                ..Group::from_players("ranked", &["d"])
+            },
+        ];
+
+        let teams = balance_teams(&groups, 2, 1000.0);
+
+        assert_eq!(teams.len(), 2);
+
+        for team in &teams {
+            assert_eq!(team.players.len(), 2);
+            assert_eq!(team.rating, 2500.0);
+        }
+    }
+
+    #[test]
+    fn teams_never_split_groups() {
+        let groups = vec![
+            Group::from_players("ranked", &["a"]),
+            Group::from_players("ranked", &["b", "c"]),
+            Group::from_players("ranked", &["d"]),
+        ];
+
+        let teams = balance_teams(&groups, 2, 1000.0);
+
+        let duo = teams
+            .iter()
+            .find(|team| team.players.contains(&String::from("b")))
+            .unwrap();
+
+        assert_eq!(duo.players, vec![String::from("b"), String::from("c")]);
+        assert_eq!(duo.rating, 2000.0);
+
+        let other = teams
+            .iter()
+            .find(|team| !team.players.contains(&String::from("b")))
+            .unwrap();
+
+        assert_eq!(other.players.len(), 2);
+        assert_eq!(other.rating, 2000.0);
+    }
+
+    #[test]
+    fn teams_default_to_a_single_team() {
+        let groups = vec![
+            Group::from_players("ranked", &["a"]),
+            Group::from_players("ranked", &["b"]),
+        ];
+
+        let teams = balance_teams(&groups, 0, 1000.0);
+
+        assert_eq!(teams.len(), 1);
+        assert_eq!(teams[0].players.len(), 2);
+    }
+}
@@ -1,2 +1,9 @@
 pub mod epsilon_queue;
 pub mod group;
+pub mod leave_request;
+pub mod matchmaking;
+pub mod offline_request;
+pub mod placement;
+pub mod queue_statistics;
+pub mod template_selector;
+pub mod transfer;
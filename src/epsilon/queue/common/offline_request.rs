@@ -0,0 +1,7 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OfflineRequest {
+    pub players: Vec<String>,
+}
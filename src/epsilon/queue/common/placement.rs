@@ -0,0 +1,67 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlacementStrategy {
+    FillFirst,
+    Spread,
+    LeastLoaded,
+}
+
+impl Default for PlacementStrategy {
+    fn default() -> Self {
+        PlacementStrategy::FillFirst
+    }
+}
+
+pub struct Candidate {
+    pub name: String,
+    pub available: i32,
+    pub slots: i32,
+}
+
+impl Candidate {
+    fn get_load(&self) -> f64 {
+        if self.slots <= 0 {
+            return 1.0;
+        }
+
+        (self.slots - self.available) as f64 / self.slots as f64
+    }
+}
+
+pub fn place_on(candidates: &mut [Candidate], name: &str, size: i32) -> Option<String> {
+    let candidate = candidates
+        .iter_mut()
+        .find(|candidate| candidate.name == name && candidate.available >= size)?;
+
+    candidate.available -= size;
+
+    Some(candidate.name.clone())
+}
+
+impl PlacementStrategy {
+    pub fn place(&self, candidates: &mut [Candidate], size: i32) -> Option<String> {
+        let candidate = candidates
+            .iter_mut()
+            .filter(|candidate| candidate.available >= size)
+            .min_by(|a, b| self.compare(a, b))?;
+
+        candidate.available -= size;
+
+        Some(candidate.name.clone())
+    }
+
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        match self {
+            PlacementStrategy::FillFirst => a.available.cmp(&b.available),
+            PlacementStrategy::Spread => b.available.cmp(&a.available),
+            PlacementStrategy::LeastLoaded => a
+                .get_load()
+                .partial_cmp(&b.get_load())
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+const STATISTICS_WINDOW: Duration = Duration::from_secs(300);
+
+// Rolling statistics over the last few minutes of a queue
+pub struct QueueStatistics {
+    created_at: Instant,
+
+    joined: VecDeque<(Instant, usize)>,
+    dispatched: VecDeque<(Instant, usize, Duration)>,
+    abandoned: VecDeque<(Instant, usize)>,
+}
+
+impl QueueStatistics {
+    pub fn new() -> Self {
+        Self {
+            created_at: Instant::now(),
+
+            joined: VecDeque::new(),
+            dispatched: VecDeque::new(),
+            abandoned: VecDeque::new(),
+        }
+    }
+
+    pub fn record_join(&mut self, players: usize) {
+        let now = Instant::now();
+
+        Self::prune(&mut self.joined, now, |(instant, _)| *instant);
+        self.joined.push_back((now, players));
+    }
+
+    pub fn record_dispatch(&mut self, players: usize, waited: Duration) {
+        let now = Instant::now();
+
+        Self::prune(&mut self.dispatched, now, |(instant, _, _)| *instant);
+        self.dispatched.push_back((now, players, waited));
+    }
+
+    pub fn record_abandon(&mut self, players: usize) {
+        if players == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+
+        Self::prune(&mut self.abandoned, now, |(instant, _)| *instant);
+        self.abandoned.push_back((now, players));
+    }
+
+    pub fn get_estimated_wait(&self, players_ahead: usize) -> Option<u64> {
+        let now = Instant::now();
+
+        let dispatched_players = self.get_dispatched_players(now);
+
+        if dispatched_players == 0 {
+            return None;
+        }
+
+        Some((players_ahead + 1) as u64 * self.get_span(now).as_secs() / dispatched_players as u64)
+    }
+
+    pub fn to_json(&self, name: &str, players: usize) -> QueueStatisticsJson {
+        let now = Instant::now();
+
+        let mut waits: Vec<f64> = self
+            .dispatched
+            .iter()
+            .filter(|(instant, _, _)| Self::is_recent(now, *instant))
+            .flat_map(|(_, players, waited)| vec![waited.as_secs_f64(); *players])
+            .collect();
+        waits.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let average_wait = if waits.is_empty() {
+            None
+        } else {
+            Some(waits.iter().sum::<f64>() / waits.len() as f64)
+        };
+
+        let p95_wait = match waits.len() {
+            0 => None,
+            length => Some(waits[(length * 95 / 100).min(length - 1)]),
+        };
+
+        let joined: usize = self
+            .joined
+            .iter()
+            .filter(|(instant, _)| Self::is_recent(now, *instant))
+            .map(|(_, players)| players)
+            .sum();
+
+        let abandoned: usize = self
+            .abandoned
+            .iter()
+            .filter(|(instant, _)| Self::is_recent(now, *instant))
+            .map(|(_, players)| players)
+            .sum();
+
+        let dispatched = self.get_dispatched_players(now);
+
+        let span = self.get_span(now).as_secs_f64().max(1.0);
+
+        QueueStatisticsJson {
+            name: name.to_owned(),
+            players,
+            window: STATISTICS_WINDOW.as_secs(),
+
+            joined,
+            dispatched,
+            abandoned,
+
+            average_wait,
+            p95_wait,
+
+            dispatch_rate: dispatched as f64 * 60.0 / span,
+            abandonment_rate: match dispatched + abandoned {
+                0 => None,
+                total => Some(abandoned as f64 / total as f64),
+            },
+        }
+    }
+
+    fn get_dispatched_players(&self, now: Instant) -> usize {
+        self.dispatched
+            .iter()
+            .filter(|(instant, _, _)| Self::is_recent(now, *instant))
+            .map(|(_, players, _)| players)
+            .sum()
+    }
+
+    // Shorter than the window right after startup, so rates are not underestimated
+    fn get_span(&self, now: Instant) -> Duration {
+        now.duration_since(self.created_at).min(STATISTICS_WINDOW)
+    }
+
+    fn is_recent(now: Instant, instant: Instant) -> bool {
+        now.duration_since(instant) <= STATISTICS_WINDOW
+    }
+
+    fn prune<T, F>(records: &mut VecDeque<T>, now: Instant, get_instant: F)
+    where
+        F: Fn(&T) -> Instant,
+    {
+        while let Some(record) = records.front() {
+            if Self::is_recent(now, get_instant(record)) {
+                break;
+            }
+
+            records.pop_front();
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueueStatisticsJson {
+    pub name: String,
+    pub players: usize,
+    pub window: u64,
+
+    pub joined: usize,
+    pub dispatched: usize,
+    pub abandoned: usize,
+
+    pub average_wait: Option<f64>,
+    pub p95_wait: Option<f64>,
+
+    pub dispatch_rate: f64,
+    pub abandonment_rate: Option<f64>,
+}
@@ -0,0 +1,149 @@
+struct WeightedTemplate {
+    name: String,
+    weight: i64,
+    current: i64,
+}
+
+// Smooth weighted round-robin, templates are picked in proportion to their weight and
+// interleaved instead of in bursts. Equal weights give a plain rotation.
+pub struct TemplateSelector {
+    templates: Vec<WeightedTemplate>,
+}
+
+impl TemplateSelector {
+    pub fn new(templates: Vec<(String, u32)>) -> Self {
+        Self {
+            templates: templates
+                .into_iter()
+                .map(|(name, weight)| WeightedTemplate {
+                    name,
+                    weight: weight as i64,
+                    current: 0,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn next(&mut self) -> Option<String> {
+        let total: i64 = self.templates.iter().map(|template| template.weight).sum();
+
+        if total <= 0 {
+            return self.templates.first().map(|template| template.name.clone());
+        }
+
+        for template in self.templates.iter_mut() {
+            template.current += template.weight;
+        }
+
+        let selected = self
+            .templates
+            .iter_mut()
+            .filter(|template| template.weight > 0)
+            .max_by_key(|template| template.current)?;
+
+        selected.current -= total;
+
+        Some(selected.name.clone())
+    }
+
+    pub fn get_templates(&self) -> Vec<&str> {
+        self.templates
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::TemplateSelector;
+
+    fn pick(selector: &mut TemplateSelector, count: usize) -> Vec<String> {
+        (0..count).filter_map(|_| selector.next()).collect()
+    }
+
+    #[test]
+    fn picks_in_proportion_to_weight() {
+        let mut selector = TemplateSelector::new(vec![
+            (String::from("a"), 5),
+            (String::from("b"), 1),
+            (String::from("c"), 1),
+        ]);
+
+        // Every cycle of the total weight gives each template exactly its weight
+        for _ in 0..3 {
+            let mut counts = HashMap::new();
+
+            for name in pick(&mut selector, 7) {
+                *counts.entry(name).or_insert(0) += 1;
+            }
+
+            assert_eq!(counts.get("a"), Some(&5));
+            assert_eq!(counts.get("b"), Some(&1));
+            assert_eq!(counts.get("c"), Some(&1));
+        }
+    }
+
+    #[test]
+    fn heavy_template_is_interleaved() {
+        let mut selector = TemplateSelector::new(vec![
+            (String::from("a"), 5),
+            (String::from("b"), 1),
+            (String::from("c"), 1),
+        ]);
+
+        let picks = pick(&mut selector, 7);
+        let bursts = picks
+            .windows(3)
+            .filter(|window| window.iter().all(|name| name == "a"))
+            .count();
+
+        assert_eq!(bursts, 0, "{:?}", picks);
+    }
+
+    #[test]
+    fn equal_weights_rotate() {
+        let mut selector = TemplateSelector::new(vec![
+            (String::from("a"), 1),
+            (String::from("b"), 1),
+            (String::from("c"), 1),
+        ]);
+
+        let picks = pick(&mut selector, 6);
+
+        for window in picks.chunks(3) {
+            let mut names = window.to_vec();
+            names.sort();
+
+            assert_eq!(names, vec!["a", "b", "c"]);
+        }
+
+        assert_eq!(picks[..3], picks[3..]);
+    }
+
+    #[test]
+    fn zero_weight_is_never_picked() {
+        let mut selector =
+            TemplateSelector::new(vec![(String::from("a"), 2), (String::from("b"), 0)]);
+
+        assert!(pick(&mut selector, 10).iter().all(|name| name == "a"));
+    }
+
+    #[test]
+    fn all_zero_weights_fall_back_to_first() {
+        let mut selector =
+            TemplateSelector::new(vec![(String::from("a"), 0), (String::from("b"), 0)]);
+
+        assert_eq!(pick(&mut selector, 3), vec!["a", "a", "a"]);
+    }
+
+    #[test]
+    fn empty_selector_picks_nothing() {
+        let mut selector = TemplateSelector::new(Vec::new());
+
+        assert_eq!(selector.next(), None);
+        assert!(selector.get_templates().is_empty());
+    }
+}
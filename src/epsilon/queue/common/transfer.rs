@@ -0,0 +1,21 @@
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::epsilon::queue::common::group::Group;
+
+pub struct Transfer {
+    pub id: u64,
+    pub group: Group,
+    pub server: String,
+
+    pub attempts: u32,
+    pub sent_at: Instant,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TransferAck {
+    pub success: bool,
+    pub reason: Option<String>,
+}
@@ -1,4 +1,6 @@
 pub mod queue_provider;
+pub mod transfer_provider;
 
 pub mod common;
 pub mod routes;
+pub mod store;
@@ -1,31 +1,1014 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::api::common::metrics::MetricsWriter;
+use crate::epsilon::api::common::rate_limiter::RateLimiter;
 use crate::epsilon::epsilon_error::EpsilonError;
-use crate::epsilon::queue::common::epsilon_queue::Queue;
-use crate::{InstanceProvider, TemplateProvider};
+use crate::epsilon::queue::common::epsilon_queue::{
+    PlayerPositionJson, Queue, QueueDetailsJson, QueueJoinJson, QueueJson,
+};
+use crate::epsilon::queue::common::group::{is_valid_player, Group, JoinTarget};
+use crate::epsilon::queue::common::placement::PlacementStrategy;
+use crate::epsilon::queue::common::queue_statistics::QueueStatisticsJson;
+use crate::epsilon::queue::store::queue_store::{
+    LoggedOperation, QueueOperation, QueueSnapshot, QueueStore, StoredEntry,
+};
+use crate::epsilon::server::templates::template::Template;
+use crate::{EpsilonApi, EpsilonConfig, InstanceProvider, TemplateProvider};
+
+type PlayerRegistry = HashMap<String, HashSet<String>>;
+
+// Groups taken by the matchmaker, they stay registered until the match hands them to a transfer
+pub struct PendingMatch {
+    pub queue: String,
+    pub groups: Vec<Group>,
+
+    pub instance: Option<String>,
+    pub formed_at: Instant,
+}
+
+struct Persistence {
+    store: Box<dyn QueueStore>,
+
+    sequence: AtomicU64,
+    // Operations waiting for the next flush, in sequence order
+    pending: Mutex<Vec<LoggedOperation>>,
+    // Held while the store is written, so batches and snapshots never interleave
+    writing: Mutex<()>,
+
+    logged: AtomicUsize,
+    compact_after: usize,
+    needs_snapshot: AtomicBool,
+}
 
 pub struct QueueProvider {
+    epsilon_api: Arc<EpsilonApi>,
     queue_map: HashMap<String, RwLock<Queue>>,
+
+    // Always locked before the matches and the queues so that moves between queues are atomic
+    players: Mutex<PlayerRegistry>,
+    single_queue: bool,
+
+    matches: Mutex<HashMap<u64, PendingMatch>>,
+    next_match: AtomicU64,
+
+    // Last position and players ahead sent to each group, by queue and first player
+    positions: Mutex<HashMap<(String, String), (usize, usize)>>,
+
+    ranks: HashMap<String, i32>,
+    max_skips: u32,
+    placement: PlacementStrategy,
+    target_timeout: Duration,
+    match_start_timeout: Duration,
+
+    player_limiter: RateLimiter,
+    caller_limiter: RateLimiter,
+
+    persistence: Option<Persistence>,
 }
 
 impl QueueProvider {
     pub async fn new(
         _instance_provider: &InstanceProvider,
         template_provider: &Arc<TemplateProvider>,
+        epsilon_api: &Arc<EpsilonApi>,
+        config: &Arc<EpsilonConfig>,
+        queue_store: Option<Box<dyn QueueStore>>,
     ) -> Result<QueueProvider, EpsilonError> {
+        let templates = template_provider.get_templates().await?;
+
         let mut map = HashMap::new();
 
-        for template in template_provider.get_templates().await? {
-            map.insert(template.name.to_owned(), RwLock::new(Queue::new()));
+        for template in &templates {
+            map.insert(
+                template.name.to_owned(),
+                RwLock::new(Self::create_queue(config, &template.name, &[(template, 1)])),
+            );
+        }
+
+        for (queue_name, weighted_templates) in &config.queue.templates {
+            let mut queue_templates = Vec::with_capacity(weighted_templates.len());
+
+            for weighted_template in weighted_templates {
+                match templates
+                    .iter()
+                    .find(|template| template.name == weighted_template.template)
+                {
+                    Some(template) => queue_templates.push((template, weighted_template.weight)),
+                    None => warn!(
+                        "Queue template not found (queue={}, template={})",
+                        queue_name, weighted_template.template
+                    ),
+                }
+            }
+
+            if queue_templates.is_empty() {
+                warn!(
+                    "Queue has no template, it is not created (name={})",
+                    queue_name
+                );
+                continue;
+            }
+
+            let queue = Self::create_queue(config, queue_name, &queue_templates);
+
+            if map
+                .insert(queue_name.to_owned(), RwLock::new(queue))
+                .is_some()
+            {
+                warn!(
+                    "Queue replaces the queue of the template with the same name (name={})",
+                    queue_name
+                );
+            }
+        }
+
+        let mut players = HashMap::new();
+        let mut sequence = 0;
+
+        if let Some(store) = &queue_store {
+            let (mut snapshot, operations) = store.load().await?;
+
+            for operation in operations {
+                snapshot.apply(operation);
+            }
+
+            sequence = snapshot.sequence;
+
+            for (queue_name, entries) in snapshot.queues {
+                match map.get_mut(&queue_name) {
+                    Some(queue) => {
+                        for entry in &entries {
+                            Self::register(&mut players, &entry.group);
+                        }
+
+                        info!(
+                            "Queue restored (name={}, groups={})",
+                            queue_name,
+                            entries.len()
+                        );
+
+                        queue.get_mut().restore(entries);
+                    }
+                    None => warn!(
+                        "Queue without template dropped on restore (name={})",
+                        queue_name
+                    ),
+                }
+            }
         }
 
-        Ok(QueueProvider { queue_map: map })
+        let queue_provider = QueueProvider {
+            epsilon_api: Arc::clone(epsilon_api),
+            queue_map: map,
+
+            players: Mutex::new(players),
+            single_queue: config.queue.single_queue,
+
+            matches: Mutex::new(HashMap::new()),
+            next_match: AtomicU64::new(1),
+
+            positions: Mutex::new(HashMap::new()),
+
+            ranks: config.queue.priority.ranks.clone(),
+            max_skips: config.queue.max_skips,
+            placement: config.queue.placement,
+            target_timeout: Duration::from_secs(config.queue.target_timeout),
+            match_start_timeout: Duration::from_secs(config.queue.match_start_timeout),
+
+            player_limiter: RateLimiter::new(&config.queue.limits.player),
+            caller_limiter: RateLimiter::new(&config.queue.limits.caller),
+
+            persistence: queue_store.map(|store| Persistence {
+                store,
+
+                sequence: AtomicU64::new(sequence),
+                pending: Mutex::new(Vec::new()),
+                writing: Mutex::new(()),
+
+                logged: AtomicUsize::new(0),
+                compact_after: config.queue.persistence.compact_after.max(1),
+                needs_snapshot: AtomicBool::new(false),
+            }),
+        };
+
+        // Starts from a snapshot of the restored queues, the replayed log is no longer needed
+        queue_provider.compact().await;
+
+        Ok(queue_provider)
+    }
+
+    fn create_queue(
+        config: &EpsilonConfig,
+        queue_name: &str,
+        templates: &[(&Template, u32)],
+    ) -> Queue {
+        Queue::new(
+            templates,
+            config.queue.priority.get_policy(queue_name),
+            config.queue.expiry.get_max_wait(queue_name),
+            config.queue.limits.get_max_length(queue_name),
+        )
+    }
+
+    pub async fn next_template(&self, queue_name: &str) -> Result<String, EpsilonError> {
+        self.get_queue(queue_name)?
+            .write()
+            .await
+            .next_template()
+            .ok_or_else(|| EpsilonError::QueueNotFoundError(queue_name.to_owned()))
     }
 
     pub fn get_queues(&self) -> &HashMap<String, RwLock<Queue>> {
         &self.queue_map
     }
+
+    pub fn get_placement_strategy(&self) -> PlacementStrategy {
+        self.placement
+    }
+
+    pub fn get_queue(&self, queue_name: &str) -> Result<&RwLock<Queue>, EpsilonError> {
+        self.queue_map
+            .get(queue_name)
+            .ok_or_else(|| EpsilonError::QueueNotFoundError(queue_name.to_owned()))
+    }
+
+    pub async fn push(&self, group: Group, caller: &str) -> Result<QueueJoinJson, EpsilonError> {
+        self.caller_limiter.check(caller).map_err(|retry_after| {
+            EpsilonError::RateLimitedError(caller.to_owned(), retry_after)
+        })?;
+
+        if group.players.is_empty() {
+            return Err(EpsilonError::InvalidRequestError(format!(
+                "Group pushed to queue {} has no player",
+                group.queue
+            )));
+        }
+
+        let target_player = match &group.target {
+            Some(JoinTarget::Player(player)) => Some(player),
+            _ => None,
+        };
+
+        if let Some(player) = group
+            .players
+            .iter()
+            .chain(target_player)
+            .find(|player| !is_valid_player(player))
+        {
+            return Err(EpsilonError::InvalidRequestError(format!(
+                "Player {:?} pushed to queue {} is neither a name nor a UUID",
+                player, group.queue
+            )));
+        }
+
+        let unique_players: HashSet<&String> = group.players.iter().collect();
+
+        if unique_players.len() != group.players.len() {
+            return Err(EpsilonError::InvalidRequestError(format!(
+                "Group pushed to queue {} contains the same player twice",
+                group.queue
+            )));
+        }
+
+        let target_queue = self.get_queue(&group.queue)?;
+
+        let (max_group_size, max_length, is_match) = {
+            let queue = target_queue.read().await;
+
+            (
+                queue.get_max_group_size(),
+                queue.get_max_length(),
+                queue.get_match_settings().is_some(),
+            )
+        };
+
+        if is_match && group.target.is_some() {
+            return Err(EpsilonError::InvalidRequestError(format!(
+                "Queue {} forms matches, a group cannot target an instance",
+                group.queue
+            )));
+        }
+
+        if group.players.len() > max_group_size {
+            return Err(EpsilonError::InvalidRequestError(format!(
+                "Group of {} players can never fit in queue {}, instances have {} slots",
+                group.players.len(),
+                group.queue,
+                max_group_size
+            )));
+        }
+
+        let mut players = self.players.lock().await;
+
+        if let Some(max_length) = max_length {
+            // Players already in this queue give their place back when they push again
+            let queued_players = group
+                .players
+                .iter()
+                .filter(|player| {
+                    players
+                        .get(*player)
+                        .map_or(false, |queues| queues.contains(&group.queue))
+                })
+                .count();
+
+            let player_count = target_queue.read().await.get_player_count();
+
+            if player_count.saturating_sub(queued_players) + group.players.len() > max_length {
+                return Err(EpsilonError::QueueFullError(format!(
+                    "{} already holds {} of its {} players",
+                    group.queue, player_count, max_length
+                )));
+            }
+        }
+
+        // Checked last, so a rejected push costs the players nothing
+        self.player_limiter
+            .check_all(group.players.iter().map(String::as_str))
+            .map_err(|(player, retry_after)| {
+                EpsilonError::RateLimitedError(player.to_owned(), retry_after)
+            })?;
+
+        for player in &group.players {
+            let previous_queues: Vec<String> = players
+                .get(player)
+                .into_iter()
+                .flatten()
+                .filter(|queue_name| self.single_queue || **queue_name == group.queue)
+                .cloned()
+                .collect();
+
+            for queue_name in previous_queues {
+                self.remove_player(&mut players, player, &queue_name).await;
+            }
+        }
+
+        let (entry, join_json) = {
+            let mut queue = target_queue.write().await;
+
+            let entry = queue
+                .push(group.clone(), self.get_priority(&group))
+                .to_stored();
+
+            let (position, players_ahead, priority) =
+                queue.get_position(&group.players[0]).unwrap_or_default();
+
+            let join_json = QueueJoinJson {
+                queue: group.queue.clone(),
+                position,
+                players_ahead,
+                priority,
+
+                estimated_wait: queue.get_estimated_wait(players_ahead),
+            };
+
+            (entry, join_json)
+        };
+
+        Self::register(&mut players, &group);
+
+        self.persist(QueueOperation::Push {
+            queue: group.queue.clone(),
+            entry,
+        })
+        .await;
+
+        info!(
+            "Player {} added to queue {} (priority={})",
+            group.players.join("/"),
+            group.queue,
+            join_json.priority
+        );
+
+        self.epsilon_api
+            .send(EpsilonEvent::QueueJoined {
+                queue: group.queue.clone(),
+                group,
+            })
+            .ok();
+
+        Ok(join_json)
+    }
+
+    pub async fn pop_placeable<T, F>(
+        &self,
+        queue_name: &str,
+        place: F,
+    ) -> Result<Vec<(Group, T)>, EpsilonError>
+    where
+        F: FnMut(&Group) -> Option<T>,
+    {
+        let queue = self.get_queue(queue_name)?;
+
+        let mut players = self.players.lock().await;
+
+        let placed = queue.write().await.pop_placeable(self.max_skips, place);
+
+        for (group, _) in &placed {
+            self.release(&mut players, &group.players, queue_name).await;
+        }
+
+        Ok(placed)
+    }
+
+    // The roster becomes a pending match, its players can still leave until it starts
+    pub async fn pop_roster<F>(
+        &self,
+        queue_name: &str,
+        min_players: usize,
+        accept: F,
+    ) -> Result<Option<(u64, Vec<Group>)>, EpsilonError>
+    where
+        F: FnMut(&Group) -> bool,
+    {
+        let queue = self.get_queue(queue_name)?;
+
+        let _players = self.players.lock().await;
+
+        let roster = queue
+            .write()
+            .await
+            .pop_roster(self.max_skips, min_players, accept);
+
+        if roster.is_empty() {
+            return Ok(None);
+        }
+
+        let match_id = self.next_match.fetch_add(1, Ordering::Relaxed);
+
+        self.matches.lock().await.insert(
+            match_id,
+            PendingMatch {
+                queue: queue_name.to_owned(),
+                groups: roster.clone(),
+
+                instance: None,
+                formed_at: Instant::now(),
+            },
+        );
+
+        Ok(Some((match_id, roster)))
+    }
+
+    pub async fn set_match_instance(&self, match_id: u64, instance_name: &str) {
+        if let Some(pending_match) = self.matches.lock().await.get_mut(&match_id) {
+            pending_match.instance = Some(instance_name.to_owned());
+        }
+    }
+
+    // Pending matches as (id, instance, whether the instance took too long to start)
+    pub async fn get_pending_matches(&self) -> Vec<(u64, Option<String>, bool)> {
+        self.matches
+            .lock()
+            .await
+            .iter()
+            .map(|(match_id, pending_match)| {
+                (
+                    *match_id,
+                    pending_match.instance.clone(),
+                    pending_match.formed_at.elapsed() >= self.match_start_timeout,
+                )
+            })
+            .collect()
+    }
+
+    // The match starts, its remaining players leave the queue for good
+    pub async fn take_match(&self, match_id: u64) -> Option<PendingMatch> {
+        let mut players = self.players.lock().await;
+
+        let pending_match = self.matches.lock().await.remove(&match_id)?;
+
+        for group in &pending_match.groups {
+            self.release(&mut players, &group.players, &pending_match.queue)
+                .await;
+        }
+
+        Some(pending_match)
+    }
+
+    // The match did not start, its remaining players go back to the front of the queue
+    pub async fn cancel_match(&self, match_id: u64) -> Result<(), EpsilonError> {
+        let _players = self.players.lock().await;
+
+        let pending_match = match self.matches.lock().await.remove(&match_id) {
+            Some(pending_match) => pending_match,
+            None => return Ok(()),
+        };
+
+        let queue = self.get_queue(&pending_match.queue)?;
+
+        for group in pending_match.groups {
+            info!(
+                "Player {} put back at the front of queue {}",
+                group.players.join("/"),
+                group.queue
+            );
+
+            // Players of a pending match are still in the logged queue
+            self.persist(QueueOperation::Remove {
+                queue: pending_match.queue.clone(),
+                players: group.players.clone(),
+            })
+            .await;
+
+            let entry = queue.write().await.requeue(group).to_stored();
+
+            self.persist(QueueOperation::Requeue {
+                queue: pending_match.queue.clone(),
+                entry,
+            })
+            .await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn leave(
+        &self,
+        player: &str,
+        queue_name: Option<&str>,
+    ) -> Result<Vec<String>, EpsilonError> {
+        if let Some(queue_name) = queue_name {
+            self.get_queue(queue_name)?;
+        }
+
+        let mut players = self.players.lock().await;
+
+        let left_queues: Vec<String> = players
+            .get(player)
+            .into_iter()
+            .flatten()
+            .filter(|registered| queue_name.map_or(true, |queue_name| *registered == queue_name))
+            .cloned()
+            .collect();
+
+        if left_queues.is_empty() {
+            return Err(EpsilonError::PlayerNotFoundError(player.to_owned()));
+        }
+
+        for queue_name in &left_queues {
+            self.remove_player(&mut players, player, queue_name).await;
+
+            info!("Player {} left queue {}", player, queue_name);
+        }
+
+        Ok(left_queues)
+    }
+
+    // Players disconnected from the network, they are removed from every queue
+    pub async fn remove_offline(&self, offline_players: &[String]) -> Vec<String> {
+        let mut players = self.players.lock().await;
+
+        let mut removed = Vec::new();
+
+        for player in offline_players {
+            let queue_names: Vec<String> =
+                players.get(player).into_iter().flatten().cloned().collect();
+
+            for queue_name in &queue_names {
+                self.remove_player(&mut players, player, queue_name).await;
+
+                info!(
+                    "Offline player {} removed from queue {}",
+                    player, queue_name
+                );
+            }
+
+            if !queue_names.is_empty() {
+                removed.push(player.to_owned());
+            }
+        }
+
+        removed
+    }
+
+    pub async fn expire(&self) {
+        let mut players = self.players.lock().await;
+
+        for (queue_name, queue) in &self.queue_map {
+            let expired = queue.write().await.expire();
+
+            for group in expired {
+                self.release(&mut players, &group.players, queue_name).await;
+
+                info!(
+                    "Player {} waited too long in queue {}",
+                    group.players.join("/"),
+                    queue_name
+                );
+
+                self.epsilon_api
+                    .send(EpsilonEvent::QueueExpired {
+                        queue: queue_name.to_owned(),
+                        group,
+                    })
+                    .ok();
+            }
+        }
+    }
+
+    pub async fn drop_targets(&self) {
+        let _players = self.players.lock().await;
+
+        for (queue_name, queue) in &self.queue_map {
+            let dropped = queue.write().await.drop_targets(self.target_timeout);
+
+            for group in dropped {
+                info!(
+                    "Player {} did not reach target {:?}, joining any instance of queue {}",
+                    group.players.join("/"),
+                    group.target,
+                    queue_name
+                );
+
+                self.persist(QueueOperation::DropTarget {
+                    queue: queue_name.to_owned(),
+                    players: group.players,
+                })
+                .await;
+            }
+        }
+    }
+
+    pub async fn get_player_position(
+        &self,
+        player: &str,
+    ) -> Result<PlayerPositionJson, EpsilonError> {
+        let players = self.players.lock().await;
+
+        for queue_name in players.get(player).into_iter().flatten() {
+            let queue = self.get_queue(queue_name)?.read().await;
+
+            if let Some((position, players_ahead, priority)) = queue.get_position(player) {
+                return Ok(PlayerPositionJson {
+                    player: player.to_owned(),
+                    queue: queue_name.to_owned(),
+                    position,
+                    players_ahead,
+                    priority,
+
+                    estimated_wait: queue.get_estimated_wait(players_ahead),
+                });
+            }
+        }
+
+        Err(EpsilonError::PlayerNotFoundError(player.to_owned()))
+    }
+
+    pub async fn requeue(&self, mut group: Group) -> Result<bool, EpsilonError> {
+        let queue = self.get_queue(&group.queue)?;
+
+        let mut players = self.players.lock().await;
+
+        // Players who joined a queue in the meantime keep their new place
+        group.players.retain(|player| !players.contains_key(player));
+
+        if group.players.is_empty() {
+            return Ok(false);
+        }
+
+        info!(
+            "Player {} put back at the front of queue {}",
+            group.players.join("/"),
+            group.queue
+        );
+
+        Self::register(&mut players, &group);
+
+        let entry = queue.write().await.requeue(group).to_stored();
+
+        self.persist(QueueOperation::Requeue {
+            queue: entry.group.queue.clone(),
+            entry,
+        })
+        .await;
+
+        Ok(true)
+    }
+
+    pub async fn get_queue_json(&self, queue_name: &str) -> Result<QueueJson, EpsilonError> {
+        Ok(self.get_queue(queue_name)?.read().await.to_json(queue_name))
+    }
+
+    pub async fn get_queue_details_json(
+        &self,
+        queue_name: &str,
+    ) -> Result<QueueDetailsJson, EpsilonError> {
+        Ok(self
+            .get_queue(queue_name)?
+            .read()
+            .await
+            .to_details_json(queue_name))
+    }
+
+    pub async fn get_queue_statistics_json(
+        &self,
+        queue_name: &str,
+    ) -> Result<QueueStatisticsJson, EpsilonError> {
+        Ok(self
+            .get_queue(queue_name)?
+            .read()
+            .await
+            .to_statistics_json(queue_name))
+    }
+
+    // Only changed positions are sent, so the replay history is not filled with the same updates
+    pub async fn send_positions(&self) {
+        let mut positions = self.positions.lock().await;
+        let mut sent_positions = HashMap::with_capacity(positions.len());
+
+        for (queue_name, queue) in &self.queue_map {
+            let queue = queue.read().await;
+
+            for (group, position, players_ahead) in queue.get_positions() {
+                let key = match group.players.first() {
+                    Some(player) => (queue_name.to_owned(), player.to_owned()),
+                    None => continue,
+                };
+
+                let unchanged = positions.get(&key) == Some(&(position, players_ahead));
+
+                sent_positions.insert(key, (position, players_ahead));
+
+                if unchanged {
+                    continue;
+                }
+
+                self.epsilon_api
+                    .send(EpsilonEvent::QueuePosition {
+                        queue: queue_name.to_owned(),
+                        group: group.clone(),
+                        position,
+                        players_ahead,
+
+                        estimated_wait: queue.get_estimated_wait(players_ahead),
+                    })
+                    .ok();
+            }
+        }
+
+        // Groups no longer queued are dropped
+        *positions = sent_positions;
+    }
+
+    pub async fn write_metrics(&self, writer: &mut MetricsWriter) {
+        let mut statistics = Vec::with_capacity(self.queue_map.len());
+
+        for (queue_name, queue) in &self.queue_map {
+            statistics.push(queue.read().await.to_statistics_json(queue_name));
+        }
+
+        let samples = |get_value: fn(&QueueStatisticsJson) -> f64| -> Vec<(String, f64)> {
+            statistics
+                .iter()
+                .map(|statistics| (statistics.name.clone(), get_value(statistics)))
+                .collect()
+        };
+
+        writer
+            .gauges(
+                "epsilon_queue_players",
+                "Players waiting in the queue",
+                "queue",
+                samples(|statistics| statistics.players as f64),
+            )
+            .gauges(
+                "epsilon_queue_wait_average_seconds",
+                "Average wait of dispatched players over the statistics window",
+                "queue",
+                samples(|statistics| statistics.average_wait.unwrap_or(0.0)),
+            )
+            .gauges(
+                "epsilon_queue_wait_p95_seconds",
+                "95th percentile wait of dispatched players over the statistics window",
+                "queue",
+                samples(|statistics| statistics.p95_wait.unwrap_or(0.0)),
+            )
+            .gauges(
+                "epsilon_queue_dispatch_rate",
+                "Players dispatched per minute",
+                "queue",
+                samples(|statistics| statistics.dispatch_rate),
+            )
+            .gauges(
+                "epsilon_queue_abandonment_rate",
+                "Share of players who left the queue before being dispatched",
+                "queue",
+                samples(|statistics| statistics.abandonment_rate.unwrap_or(0.0)),
+            );
+    }
+
+    pub async fn get_queues_json(&self) -> Vec<QueueJson> {
+        let mut json_array = Vec::with_capacity(self.queue_map.len());
+
+        for (queue_name, queue) in &self.queue_map {
+            json_array.push(queue.read().await.to_json(queue_name));
+        }
+
+        json_array
+    }
+
+    pub fn get_priority(&self, group: &Group) -> i32 {
+        group
+            .priority
+            .or_else(|| {
+                group
+                    .rank
+                    .as_ref()
+                    .and_then(|rank| self.ranks.get(rank).copied())
+            })
+            .unwrap_or(0)
+    }
+
+    async fn remove_player(&self, players: &mut PlayerRegistry, player: &str, queue_name: &str) {
+        if let Some(queue) = self.queue_map.get(queue_name) {
+            queue.write().await.leave(player);
+        }
+
+        for pending_match in self
+            .matches
+            .lock()
+            .await
+            .values_mut()
+            .filter(|pending_match| pending_match.queue == queue_name)
+        {
+            for group in &mut pending_match.groups {
+                group.players.retain(|matched| matched != player);
+            }
+
+            pending_match
+                .groups
+                .retain(|group| !group.players.is_empty());
+        }
+
+        self.release(players, &[player.to_owned()], queue_name)
+            .await;
+
+        self.epsilon_api
+            .send(EpsilonEvent::QueueLeft {
+                queue: queue_name.to_owned(),
+                players: vec![player.to_owned()],
+            })
+            .ok();
+    }
+
+    async fn release(
+        &self,
+        players: &mut PlayerRegistry,
+        group_players: &[String],
+        queue_name: &str,
+    ) {
+        Self::unregister(players, group_players, queue_name);
+
+        self.persist(QueueOperation::Remove {
+            queue: queue_name.to_owned(),
+            players: group_players.to_vec(),
+        })
+        .await;
+    }
+
+    // Called with the player registry locked, so operations are numbered in the order they happen.
+    // Nothing is written here, the store is only touched by `flush`, outside of the lock
+    async fn persist(&self, operation: QueueOperation) {
+        let persistence = match &self.persistence {
+            Some(persistence) => persistence,
+            None => return,
+        };
+
+        let sequence = persistence.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+
+        persistence.pending.lock().await.push(LoggedOperation {
+            sequence,
+            operation,
+        });
+    }
+
+    pub async fn flush(&self) {
+        let persistence = match &self.persistence {
+            Some(persistence) => persistence,
+            None => return,
+        };
+
+        let _writing = persistence.writing.lock().await;
+
+        let operations = std::mem::take(&mut *persistence.pending.lock().await);
+
+        if !persistence.store.has_log() {
+            if !operations.is_empty() || persistence.needs_snapshot.load(Ordering::Relaxed) {
+                self.write_snapshot(persistence).await;
+            }
+
+            return;
+        }
+
+        if !operations.is_empty() {
+            match persistence.store.append(&operations).await {
+                Ok(()) => {
+                    persistence
+                        .logged
+                        .fetch_add(operations.len(), Ordering::Relaxed);
+                }
+                // A snapshot also recovers the operations that could not be logged
+                Err(error) => {
+                    warn!(
+                        "Failed to log queue operations (sequence={}): {}",
+                        operations[0].sequence, error
+                    );
+
+                    persistence.needs_snapshot.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        if persistence.needs_snapshot.load(Ordering::Relaxed)
+            || persistence.logged.load(Ordering::Relaxed) >= persistence.compact_after
+        {
+            self.write_snapshot(persistence).await;
+        }
+    }
+
+    async fn compact(&self) {
+        if let Some(persistence) = &self.persistence {
+            let _writing = persistence.writing.lock().await;
+
+            self.write_snapshot(persistence).await;
+        }
+    }
+
+    // Called with the writing lock held, the registry is only locked while the snapshot is built
+    async fn write_snapshot(&self, persistence: &Persistence) {
+        let snapshot = {
+            let _players = self.players.lock().await;
+
+            let mut snapshot = QueueSnapshot {
+                sequence: persistence.sequence.load(Ordering::Relaxed),
+                queues: HashMap::with_capacity(self.queue_map.len()),
+            };
+
+            for (queue_name, queue) in &self.queue_map {
+                snapshot
+                    .queues
+                    .insert(queue_name.to_owned(), queue.read().await.to_stored());
+            }
+
+            // Players of pending matches are back at the front of their queue after a restart
+            for pending_match in self.matches.lock().await.values() {
+                let entries = snapshot
+                    .queues
+                    .entry(pending_match.queue.to_owned())
+                    .or_default();
+
+                for group in pending_match.groups.iter().rev() {
+                    entries.insert(0, StoredEntry::requeued(group.clone()));
+                }
+            }
+
+            // Operations not written yet are part of the snapshot
+            persistence.pending.lock().await.clear();
+
+            snapshot
+        };
+
+        match persistence.store.compact(&snapshot).await {
+            Ok(()) => {
+                persistence.logged.store(0, Ordering::Relaxed);
+                persistence.needs_snapshot.store(false, Ordering::Relaxed);
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to write queue snapshot (sequence={}): {}",
+                    snapshot.sequence, error
+                );
+
+                persistence.needs_snapshot.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn register(players: &mut PlayerRegistry, group: &Group) {
+        for player in &group.players {
+            players
+                .entry(player.to_owned())
+                .or_default()
+                .insert(group.queue.to_owned());
+        }
+    }
+
+    fn unregister(players: &mut PlayerRegistry, group_players: &[String], queue_name: &str) {
+        for player in group_players {
+            if let Some(queues) = players.get_mut(player) {
+                queues.remove(queue_name);
+
+                if queues.is_empty() {
+                    players.remove(player);
+                }
+            }
+        }
+    }
 }
@@ -3,30 +3,118 @@ use std::sync::Arc;
 use rocket::serde::json::Json;
 use rocket::State;
 
+use crate::epsilon::auth::guards::{ProxyAccess, ReadAccess};
 use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::common::epsilon_queue::{
+    PlayerPositionJson, QueueDetailsJson, QueueJoinJson,
+};
 use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::queue::common::leave_request::LeaveRequest;
+use crate::epsilon::queue::common::offline_request::OfflineRequest;
+use crate::epsilon::queue::common::queue_statistics::QueueStatisticsJson;
+use crate::epsilon::queue::common::transfer::TransferAck;
 use crate::Context;
 
+#[rocket_okapi::openapi(tag = "Queue")]
 #[rocket::post("/push", data = "<body>")]
-pub async fn push(body: Json<Group>, context: &State<Arc<Context>>) -> Result<(), EpsilonError> {
+pub async fn push(
+    body: Json<Group>,
+    auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> Result<Json<QueueJoinJson>, EpsilonError> {
     let queue_provider = context.get_queue_provider();
 
-    let queue_name = &body.queue;
-    let queue_map = queue_provider.get_queues();
+    Ok(Json(
+        queue_provider
+            .push(body.into_inner(), &auth.get_caller())
+            .await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::post("/transfer/<id>/ack", data = "<body>")]
+pub async fn acknowledge(
+    id: u64,
+    body: Json<TransferAck>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> Result<(), EpsilonError> {
+    context
+        .get_transfer_provider()
+        .acknowledge(
+            id,
+            &body,
+            context.get_queue_provider(),
+            context.get_instance_provider(),
+        )
+        .await
+}
+
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::post("/leave", data = "<body>")]
+pub async fn leave(
+    body: Json<LeaveRequest>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> Result<Json<Vec<String>>, EpsilonError> {
+    let queue_provider = context.get_queue_provider();
 
-    let mut queue = queue_map
-        .get(queue_name)
-        .ok_or(EpsilonError::QueueNotFoundError(queue_name.to_owned()))?
-        .write()
+    Ok(Json(
+        queue_provider
+            .leave(&body.player, body.queue.as_deref())
+            .await?,
+    ))
+}
+
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::post("/offline", data = "<body>")]
+pub async fn offline(
+    body: Json<OfflineRequest>,
+    _auth: ProxyAccess,
+    context: &State<Arc<Context>>,
+) -> Json<Vec<String>> {
+    let queue_provider = context.get_queue_provider();
+
+    context
+        .get_transfer_provider()
+        .forget_players(&body.players, context.get_instance_provider())
         .await;
 
-    info!(
-        "Player {} added to queue {}",
-        &body.players.join("/"),
-        &body.queue
-    );
+    Json(queue_provider.remove_offline(&body.players).await)
+}
 
-    queue.push(body.into_inner());
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::get("/<name>")]
+pub async fn get(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> Result<Json<QueueDetailsJson>, EpsilonError> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(Json(queue_provider.get_queue_details_json(name).await?))
+}
+
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::get("/<name>/stats", rank = 2)]
+pub async fn stats(
+    name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> Result<Json<QueueStatisticsJson>, EpsilonError> {
+    let queue_provider = context.get_queue_provider();
+
+    Ok(Json(queue_provider.get_queue_statistics_json(name).await?))
+}
+
+#[rocket_okapi::openapi(tag = "Queue")]
+#[rocket::get("/player/<uuid>")]
+pub async fn player(
+    uuid: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> Result<Json<PlayerPositionJson>, EpsilonError> {
+    let queue_provider = context.get_queue_provider();
 
-    Ok(())
+    Ok(Json(queue_provider.get_player_position(uuid).await?))
 }
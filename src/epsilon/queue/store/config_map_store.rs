@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client};
+use serde_json::json;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::store::queue_store::{LoggedOperation, QueueSnapshot, QueueStore};
+
+const FIELD_MANAGER: &str = "epsilon";
+
+// Config maps are limited to 1MiB, some room is left for the metadata
+const MAX_SNAPSHOT_SIZE: usize = 1_000_000;
+
+// Only holds the last snapshot, a config map is too small for a growing log
+pub struct ConfigMapQueueStore {
+    api: Api<ConfigMap>,
+    name: String,
+}
+
+impl ConfigMapQueueStore {
+    pub async fn new(namespace: &str, name: &str) -> Result<Self, EpsilonError> {
+        let client = Client::try_default().await?;
+
+        Ok(Self {
+            api: Api::namespaced(client, namespace),
+            name: name.to_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl QueueStore for ConfigMapQueueStore {
+    async fn load(&self) -> Result<(QueueSnapshot, Vec<LoggedOperation>), EpsilonError> {
+        let snapshot = match self
+            .api
+            .get_opt(&self.name)
+            .await?
+            .and_then(|config_map| config_map.data)
+            .and_then(|mut data| data.remove("snapshot"))
+        {
+            Some(json) if !json.is_empty() => serde_json::from_str(&json)
+                .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?,
+            _ => QueueSnapshot::default(),
+        };
+
+        Ok((snapshot, Vec::new()))
+    }
+
+    async fn append(&self, _operations: &[LoggedOperation]) -> Result<(), EpsilonError> {
+        Ok(())
+    }
+
+    async fn compact(&self, snapshot: &QueueSnapshot) -> Result<(), EpsilonError> {
+        let json = serde_json::to_string(snapshot)
+            .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?;
+
+        if json.len() > MAX_SNAPSHOT_SIZE {
+            return Err(EpsilonError::PersistenceError(format!(
+                "Queue snapshot is too large for a config map ({} bytes)",
+                json.len()
+            )));
+        }
+
+        let config_map = json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": { "name": self.name },
+            "data": { "snapshot": json }
+        });
+
+        self.api
+            .patch(
+                &self.name,
+                &PatchParams::apply(FIELD_MANAGER).force(),
+                &Patch::Apply(config_map),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn has_log(&self) -> bool {
+        false
+    }
+}
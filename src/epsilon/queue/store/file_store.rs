@@ -0,0 +1,121 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::store::queue_store::{LoggedOperation, QueueSnapshot, QueueStore};
+
+pub struct FileQueueStore {
+    snapshot_path: PathBuf,
+    log_path: PathBuf,
+}
+
+impl FileQueueStore {
+    pub async fn new(directory: &str) -> Result<Self, EpsilonError> {
+        fs::create_dir_all(directory)
+            .await
+            .map_err(|error| EpsilonError::PersistenceError(error.to_string()))?;
+
+        let directory = Path::new(directory);
+
+        Ok(Self {
+            snapshot_path: directory.join("snapshot.json"),
+            log_path: directory.join("queue.log"),
+        })
+    }
+
+    async fn read(path: &Path) -> Result<Option<String>, EpsilonError> {
+        match fs::read_to_string(path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(EpsilonError::PersistenceError(error.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl QueueStore for FileQueueStore {
+    async fn load(&self) -> Result<(QueueSnapshot, Vec<LoggedOperation>), EpsilonError> {
+        let snapshot = match Self::read(&self.snapshot_path).await? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?,
+            None => QueueSnapshot::default(),
+        };
+
+        let mut operations = Vec::new();
+
+        for line in Self::read(&self.log_path)
+            .await?
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+        {
+            match serde_json::from_str(line) {
+                Ok(operation) => operations.push(operation),
+                // Only the last line can be cut by a crash, nothing was logged after it
+                Err(error) => {
+                    warn!("Queue log ends with an incomplete operation: {}", error);
+                    break;
+                }
+            }
+        }
+
+        Ok((snapshot, operations))
+    }
+
+    async fn append(&self, operations: &[LoggedOperation]) -> Result<(), EpsilonError> {
+        let mut lines = String::new();
+
+        for operation in operations {
+            lines.push_str(
+                &serde_json::to_string(operation)
+                    .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?,
+            );
+            lines.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .await
+            .map_err(|error| EpsilonError::PersistenceError(error.to_string()))?;
+
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(|error| EpsilonError::PersistenceError(error.to_string()))?;
+
+        file.sync_data()
+            .await
+            .map_err(|error| EpsilonError::PersistenceError(error.to_string()))
+    }
+
+    async fn compact(&self, snapshot: &QueueSnapshot) -> Result<(), EpsilonError> {
+        let json = serde_json::to_string(snapshot)
+            .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?;
+
+        let temporary_path = self.snapshot_path.with_extension("json.tmp");
+
+        // The rename is atomic, a crash leaves either the old or the new snapshot.
+        // Both are synced before the log is truncated, so a power loss cannot lose the two
+        let result = async {
+            let mut file = File::create(&temporary_path).await?;
+            file.write_all(json.as_bytes()).await?;
+            file.sync_all().await?;
+
+            fs::rename(&temporary_path, &self.snapshot_path).await?;
+
+            if let Some(directory) = self.snapshot_path.parent() {
+                File::open(directory).await?.sync_all().await?;
+            }
+
+            fs::write(&self.log_path, "").await
+        }
+        .await;
+
+        result.map_err(|error| EpsilonError::PersistenceError(error.to_string()))
+    }
+}
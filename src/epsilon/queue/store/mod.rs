@@ -0,0 +1,3 @@
+pub mod config_map_store;
+pub mod file_store;
+pub mod queue_store;
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::config::PersistenceBackend;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::queue::store::config_map_store::ConfigMapQueueStore;
+use crate::epsilon::queue::store::file_store::FileQueueStore;
+use crate::EpsilonConfig;
+
+#[async_trait]
+pub trait QueueStore: Send + Sync {
+    // Last snapshot and the operations logged since, some of them may already be in the snapshot
+    async fn load(&self) -> Result<(QueueSnapshot, Vec<LoggedOperation>), EpsilonError>;
+
+    // Operations come in batches, in sequence order
+    async fn append(&self, operations: &[LoggedOperation]) -> Result<(), EpsilonError>;
+
+    // Replaces the snapshot and clears the log
+    async fn compact(&self, snapshot: &QueueSnapshot) -> Result<(), EpsilonError>;
+
+    // A store without a log gets a new snapshot instead of each batch
+    fn has_log(&self) -> bool {
+        true
+    }
+}
+
+pub async fn create_queue_store(
+    config: &EpsilonConfig,
+    namespace: &str,
+) -> Result<Option<Box<dyn QueueStore>>, EpsilonError> {
+    let persistence = &config.queue.persistence;
+
+    Ok(match persistence.backend {
+        PersistenceBackend::Memory => None,
+        PersistenceBackend::File => {
+            Some(Box::new(FileQueueStore::new(&persistence.directory).await?))
+        }
+        PersistenceBackend::ConfigMap => Some(Box::new(
+            ConfigMapQueueStore::new(namespace, &persistence.config_map).await?,
+        )),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEntry {
+    pub group: Group,
+    pub priority: i32,
+
+    // Unix time in milliseconds, instants do not survive a restart
+    pub queued_at: u64,
+
+    pub requeued: bool,
+    pub skipped: u32,
+}
+
+impl StoredEntry {
+    pub fn requeued(group: Group) -> Self {
+        Self {
+            group,
+            priority: 0,
+            queued_at: get_unix_millis(),
+
+            requeued: true,
+            skipped: 0,
+        }
+    }
+
+    pub fn get_waited(&self) -> Duration {
+        Duration::from_millis(get_unix_millis().saturating_sub(self.queued_at))
+    }
+}
+
+pub fn get_unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum QueueOperation {
+    Push { queue: String, entry: StoredEntry },
+    Requeue { queue: String, entry: StoredEntry },
+    Remove { queue: String, players: Vec<String> },
+    DropTarget { queue: String, players: Vec<String> },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoggedOperation {
+    pub sequence: u64,
+    pub operation: QueueOperation,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    // Sequence of the last operation included
+    pub sequence: u64,
+    pub queues: HashMap<String, Vec<StoredEntry>>,
+}
+
+impl QueueSnapshot {
+    pub fn apply(&mut self, logged: LoggedOperation) {
+        if logged.sequence <= self.sequence {
+            return;
+        }
+
+        self.sequence = logged.sequence;
+
+        match logged.operation {
+            QueueOperation::Push { queue, entry } => {
+                self.queues.entry(queue).or_default().push(entry);
+            }
+            QueueOperation::Requeue { queue, entry } => {
+                self.queues.entry(queue).or_default().insert(0, entry);
+            }
+            QueueOperation::Remove { queue, players } => {
+                if let Some(entries) = self.queues.get_mut(&queue) {
+                    for entry in entries.iter_mut() {
+                        entry
+                            .group
+                            .players
+                            .retain(|player| !players.contains(player));
+                    }
+
+                    entries.retain(|entry| !entry.group.players.is_empty());
+                }
+            }
+            QueueOperation::DropTarget { queue, players } => {
+                for entry in self.queues.get_mut(&queue).into_iter().flatten() {
+                    if entry.group.players == players {
+                        entry.group.target = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoggedOperation, QueueOperation, QueueSnapshot, StoredEntry};
+    use crate::epsilon::queue::common::group::{Group, JoinTarget};
+
+    fn get_players(snapshot: &QueueSnapshot, queue: &str) -> Vec<Vec<String>> {
+        snapshot
+            .queues
+            .get(queue)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| entry.group.players.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn push_appends_and_requeue_goes_first() {
+        let mut snapshot = QueueSnapshot::default();
+
+        snapshot.apply(LoggedOperation {
+            sequence: 1,
+            operation: QueueOperation::Push {
+                queue: String::from("skywars"),
+                entry: StoredEntry::requeued(Group::from_players("skywars", &["a"])),
+            },
+        });
+        snapshot.apply(LoggedOperation {
+            sequence: 2,
+            operation: QueueOperation::Push {
+                queue: String::from("skywars"),
+                entry: StoredEntry::requeued(Group::from_players("skywars", &["b"])),
+            },
+        });
+        snapshot.apply(LoggedOperation {
+            sequence: 3,
+            operation: QueueOperation::Requeue {
+                queue: String::from("skywars"),
+                entry: StoredEntry::requeued(Group::from_players("skywars", &["c"])),
+            },
+        });
+
+        assert_eq!(snapshot.sequence, 3);
+        assert_eq!(
+            get_players(&snapshot, "skywars"),
+            vec![vec!["c"], vec!["a"], vec!["b"]]
+        );
+    }
+
+    #[test]
+    fn remove_drops_players_and_empty_entries() {
+        let mut snapshot = QueueSnapshot::default();
+
+        snapshot.queues.insert(
+            String::from("skywars"),
+            vec![
+                StoredEntry::requeued(Group::from_players("skywars", &["a", "b"])),
+                StoredEntry::requeued(Group::from_players("skywars", &["c"])),
+            ],
+        );
+
+        snapshot.apply(LoggedOperation {
+            sequence: 1,
+            operation: QueueOperation::Remove {
+                queue: String::from("skywars"),
+                players: vec![String::from("a"), String::from("c")],
+            },
+        });
+
+        assert_eq!(get_players(&snapshot, "skywars"), vec![vec!["b"]]);
+    }
+
+    #[test]
+    fn remove_from_unknown_queue_is_ignored() {
+        let mut snapshot = QueueSnapshot::default();
+
+        snapshot.apply(LoggedOperation {
+            sequence: 1,
+            operation: QueueOperation::Remove {
+                queue: String::from("bedwars"),
+                players: vec![String::from("a")],
+            },
+        });
+
+        assert_eq!(snapshot.sequence, 1);
+        assert!(snapshot.queues.is_empty());
+    }
+
+    #[test]
+    fn drop_target_clears_the_group_target() {
+        let mut snapshot = QueueSnapshot::default();
+
+        let mut entry = StoredEntry::requeued(Group::from_players("skywars", &["a", "b"]));
+        entry.group.target = Some(JoinTarget::Instance(String::from("skywars-1")));
+
+        snapshot.queues.insert(String::from("skywars"), vec![entry]);
+
+        snapshot.apply(LoggedOperation {
+            sequence: 1,
+            operation: QueueOperation::DropTarget {
+                queue: String::from("skywars"),
+                players: vec![String::from("a"), String::from("b")],
+            },
+        });
+
+        assert!(snapshot.queues["skywars"][0].group.target.is_none());
+    }
+
+    #[test]
+    fn operations_already_in_snapshot_are_skipped() {
+        let mut snapshot = QueueSnapshot {
+            sequence: 5,
+            ..Default::default()
+        };
+
+        for sequence in [4, 5] {
+            snapshot.apply(LoggedOperation {
+                sequence,
+                operation: QueueOperation::Push {
+                    queue: String::from("skywars"),
+                    entry: StoredEntry::requeued(Group::from_players("skywars", &["a"])),
+                },
+            });
+        }
+
+        assert_eq!(snapshot.sequence, 5);
+        assert!(snapshot.queues.is_empty());
+
+        snapshot.apply(LoggedOperation {
+            sequence: 6,
+            operation: QueueOperation::Push {
+                queue: String::from("skywars"),
+                entry: StoredEntry::requeued(Group::from_players("skywars", &["b"])),
+            },
+        });
+
+        assert_eq!(snapshot.sequence, 6);
+        assert_eq!(get_players(&snapshot, "skywars"), vec![vec!["b"]]);
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::api::common::metrics::MetricsWriter;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::common::group::Group;
+use crate::epsilon::queue::common::transfer::{Transfer, TransferAck};
+use crate::{EpsilonApi, EpsilonConfig, InstanceProvider, QueueProvider};
+
+#[derive(Default)]
+struct TransferMetrics {
+    sent: AtomicU64,
+    acknowledged: AtomicU64,
+    failed: AtomicU64,
+    retried: AtomicU64,
+    requeued: AtomicU64,
+}
+
+pub struct TransferProvider {
+    epsilon_api: Arc<EpsilonApi>,
+
+    transfers: RwLock<HashMap<u64, Transfer>>,
+    next_id: AtomicU64,
+
+    // Last server each player was successfully transferred to
+    locations: RwLock<HashMap<String, String>>,
+
+    track: bool,
+    timeout: Duration,
+    max_attempts: u32,
+
+    metrics: TransferMetrics,
+}
+
+impl TransferProvider {
+    pub fn new(epsilon_api: &Arc<EpsilonApi>, config: &Arc<EpsilonConfig>) -> TransferProvider {
+        Self {
+            epsilon_api: Arc::clone(epsilon_api),
+
+            transfers: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+
+            locations: RwLock::new(HashMap::new()),
+
+            track: config.transfer.track,
+            timeout: Duration::from_secs(config.transfer.timeout),
+            max_attempts: config.transfer.attempts.max(1),
+
+            metrics: TransferMetrics::default(),
+        }
+    }
+
+    pub async fn transfer(&self, group: Group, server: &str) -> u64 {
+        let transfer = Transfer {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            group,
+            server: server.to_owned(),
+
+            attempts: 1,
+            sent_at: Instant::now(),
+        };
+
+        let id = transfer.id;
+
+        self.send(&transfer);
+        self.metrics.sent.fetch_add(1, Ordering::Relaxed);
+
+        if self.track {
+            self.transfers.write().await.insert(id, transfer);
+        } else {
+            self.set_location(&transfer.server, &transfer.group.players)
+                .await;
+        }
+
+        id
+    }
+
+    pub async fn acknowledge(
+        &self,
+        id: u64,
+        ack: &TransferAck,
+        queue_provider: &QueueProvider,
+        instance_provider: &InstanceProvider,
+    ) -> Result<(), EpsilonError> {
+        if !self.track {
+            return Ok(());
+        }
+
+        let transfer = self
+            .transfers
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(EpsilonError::TransferNotFoundError(id))?;
+
+        if ack.success {
+            self.metrics.acknowledged.fetch_add(1, Ordering::Relaxed);
+
+            instance_provider
+                .release_slots(&transfer.server, &transfer.group.players)
+                .await;
+
+            self.set_location(&transfer.server, &transfer.group.players)
+                .await;
+
+            debug!(
+                "Transfer acknowledged (id={}, server={})",
+                id, transfer.server
+            );
+        } else {
+            self.metrics.failed.fetch_add(1, Ordering::Relaxed);
+
+            warn!(
+                "Transfer failed (id={}, server={}, reason={})",
+                id,
+                transfer.server,
+                ack.reason.as_deref().unwrap_or("unknown")
+            );
+
+            self.fallback(transfer, queue_provider, instance_provider)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Arrivals confirmed by the server complete the transfers of proxies that do not acknowledge
+    pub async fn confirm_arrival(&self, server: &str, players: &[String]) {
+        let completed: Vec<u64> = {
+            let mut transfers = self.transfers.write().await;
+            let mut completed_ids = Vec::new();
+
+            for transfer in transfers
+                .values_mut()
+                .filter(|transfer| transfer.server == server)
+            {
+                transfer
+                    .group
+                    .players
+                    .retain(|player| !players.contains(player));
+
+                if transfer.group.players.is_empty() {
+                    completed_ids.push(transfer.id);
+                }
+            }
+
+            completed_ids
+                .into_iter()
+                .filter(|id| transfers.remove(id).is_some())
+                .collect()
+        };
+
+        for id in completed {
+            self.metrics.acknowledged.fetch_add(1, Ordering::Relaxed);
+
+            debug!(
+                "Transfer completed by arrival (id={}, server={})",
+                id, server
+            );
+        }
+
+        self.set_location(server, players).await;
+    }
+
+    async fn set_location(&self, server: &str, players: &[String]) {
+        let mut locations = self.locations.write().await;
+
+        for player in players {
+            locations.insert(player.to_owned(), server.to_owned());
+        }
+    }
+
+    pub async fn get_player_location(&self, player: &str) -> Option<String> {
+        self.locations.read().await.get(player).cloned()
+    }
+
+    // Offline players lose their location and their transfers in flight, so they are never requeued
+    pub async fn forget_players(&self, players: &[String], instance_provider: &InstanceProvider) {
+        {
+            let mut locations = self.locations.write().await;
+
+            for player in players {
+                locations.remove(player);
+            }
+        }
+
+        let cancelled: Vec<(String, Vec<String>)> = {
+            let mut transfers = self.transfers.write().await;
+            let mut cancelled = Vec::new();
+
+            for transfer in transfers.values_mut() {
+                let (offline, online): (Vec<String>, Vec<String>) = transfer
+                    .group
+                    .players
+                    .drain(..)
+                    .partition(|player| players.contains(player));
+
+                transfer.group.players = online;
+
+                if !offline.is_empty() {
+                    debug!(
+                        "Transfer of offline player {} cancelled (id={}, server={})",
+                        offline.join("/"),
+                        transfer.id,
+                        transfer.server
+                    );
+
+                    cancelled.push((transfer.server.clone(), offline));
+                }
+            }
+
+            transfers.retain(|_, transfer| !transfer.group.players.is_empty());
+
+            cancelled
+        };
+
+        for (server, offline) in cancelled {
+            instance_provider.release_slots(&server, &offline).await;
+        }
+    }
+
+    pub async fn check_transfers(
+        &self,
+        queue_provider: &QueueProvider,
+        instance_provider: &InstanceProvider,
+    ) -> Result<(), EpsilonError> {
+        let now = Instant::now();
+
+        let expired: Vec<Transfer> = {
+            let mut transfers = self.transfers.write().await;
+            let mut expired_ids = Vec::new();
+
+            for transfer in transfers.values_mut() {
+                if now.duration_since(transfer.sent_at) < self.timeout {
+                    continue;
+                }
+
+                if transfer.attempts < self.max_attempts {
+                    transfer.attempts += 1;
+                    transfer.sent_at = now;
+
+                    self.send(transfer);
+                    self.metrics.retried.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    expired_ids.push(transfer.id);
+                }
+            }
+
+            expired_ids
+                .into_iter()
+                .filter_map(|id| transfers.remove(&id))
+                .collect()
+        };
+
+        for transfer in expired {
+            warn!(
+                "Transfer is not acknowledged in time, group goes back to queue (id={}, server={})",
+                transfer.id, transfer.server
+            );
+
+            self.fallback(transfer, queue_provider, instance_provider)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn fallback(
+        &self,
+        transfer: Transfer,
+        queue_provider: &QueueProvider,
+        instance_provider: &InstanceProvider,
+    ) -> Result<(), EpsilonError> {
+        instance_provider
+            .release_slots(&transfer.server, &transfer.group.players)
+            .await;
+
+        if queue_provider.requeue(transfer.group).await? {
+            self.metrics.requeued.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    fn send(&self, transfer: &Transfer) {
+        let event = EpsilonEvent::SendToServer {
+            group: transfer.group.clone(),
+            server: transfer.server.clone(),
+            transfer: transfer.id,
+        };
+
+        if self.epsilon_api.send(event).is_err() {
+            warn!(
+                "No proxy is listening, transfer is kept for retry (id={}, attempt={})",
+                transfer.id, transfer.attempts
+            );
+        }
+    }
+
+    pub async fn write_metrics(&self, writer: &mut MetricsWriter) {
+        let pending = self.transfers.read().await.len();
+
+        writer
+            .counter(
+                "epsilon_transfers_sent_total",
+                "Groups sent to a server",
+                self.metrics.sent.load(Ordering::Relaxed),
+            )
+            .counter(
+                "epsilon_transfers_acknowledged_total",
+                "Transfers acknowledged as successful by a proxy",
+                self.metrics.acknowledged.load(Ordering::Relaxed),
+            )
+            .counter(
+                "epsilon_transfers_failed_total",
+                "Transfers acknowledged as failed by a proxy",
+                self.metrics.failed.load(Ordering::Relaxed),
+            )
+            .counter(
+                "epsilon_transfers_retried_total",
+                "Transfers sent again after the acknowledgement timeout",
+                self.metrics.retried.load(Ordering::Relaxed),
+            )
+            .counter(
+                "epsilon_transfers_requeued_total",
+                "Groups put back at the front of their queue",
+                self.metrics.requeued.load(Ordering::Relaxed),
+            )
+            .gauge(
+                "epsilon_transfers_pending",
+                "Transfers waiting for an acknowledgement",
+                pending as f64,
+            );
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::epsilon::server::instances::common::state::EpsilonState;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AllocationRequest {
+    pub template: String,
+
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub content: Map<String, Value>,
+
+    #[serde(default = "default_slots")]
+    pub slots: i32,
+}
+
+fn default_slots() -> i32 {
+    1
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AllocationJson {
+    pub name: String,
+    pub template: String,
+
+    pub state: EpsilonState,
+    pub allocated: bool,
+
+    pub ip: Option<String>,
+    pub port: i32,
+}
@@ -0,0 +1,21 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateInstanceRequest {
+    pub template: String,
+
+    #[serde(default = "default_content")]
+    pub content: Value,
+}
+
+fn default_content() -> Value {
+    Value::Object(Map::new())
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreatedInstanceJson {
+    pub name: String,
+    pub template: String,
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use rocket::{FromForm, FromFormField};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::controller::definitions::epsilon_instance::InstanceJson;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::server::instances::common::instance_type::InstanceType;
+use crate::epsilon::server::instances::common::state::EpsilonState;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAXIMUM_LIMIT: usize = 500;
+
+#[derive(Debug, Default)]
+pub struct InstanceFilter<'a> {
+    pub t: Option<InstanceType>,
+    pub template: Option<&'a str>,
+    pub state: Option<EpsilonState>,
+
+    pub hub: Option<bool>,
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceSort {
+    Name,
+    Template,
+    State,
+    Online,
+    #[field(value = "free_slots")]
+    FreeSlots,
+}
+
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct InstanceQuery {
+    pub template: Option<String>,
+    #[field(name = "type")]
+    #[serde(rename = "type")]
+    pub t: Option<InstanceType>,
+    pub state: Option<EpsilonState>,
+
+    pub hub: Option<bool>,
+    pub label: Vec<String>,
+    pub min_free_slots: Option<i32>,
+
+    pub sort: Option<InstanceSort>,
+    pub desc: Option<bool>,
+
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl InstanceQuery {
+    pub fn to_filter(&self) -> Result<InstanceFilter, EpsilonError> {
+        let mut labels = BTreeMap::new();
+
+        for label in &self.label {
+            let (key, value) = label.split_once('=').ok_or_else(|| {
+                EpsilonError::InvalidRequestError(format!(
+                    "Label selector {} is not formatted as key=value",
+                    label
+                ))
+            })?;
+
+            labels.insert(key.to_owned(), value.to_owned());
+        }
+
+        Ok(InstanceFilter {
+            t: self.t,
+            template: self.template.as_deref(),
+            state: self.state,
+
+            hub: self.hub,
+            labels,
+        })
+    }
+
+    pub fn get_offset(&self) -> Result<usize, EpsilonError> {
+        match &self.cursor {
+            None => Ok(0),
+            Some(cursor) => cursor.parse::<usize>().map_err(|_| {
+                EpsilonError::InvalidRequestError(format!("Cursor {} is invalid", cursor))
+            }),
+        }
+    }
+
+    pub fn get_limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAXIMUM_LIMIT)
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct InstancePage {
+    pub instances: Vec<InstanceJson>,
+
+    pub total: usize,
+    pub next_cursor: Option<String>,
+}
@@ -1,8 +1,9 @@
 use k8s_openapi::api::core::v1::ContainerPort;
+use rocket::FromFormField;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema, FromFormField)]
 pub enum InstanceType {
     Server,
     Proxy,
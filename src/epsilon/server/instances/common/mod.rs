@@ -1,2 +1,5 @@
+pub mod allocation;
+pub mod creation;
+pub mod instance_query;
 pub mod instance_type;
 pub mod state;
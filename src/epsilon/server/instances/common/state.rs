@@ -1,7 +1,20 @@
+use rocket::FromFormField;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, JsonSchema)]
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    JsonSchema,
+    FromFormField,
+)]
 pub enum EpsilonState {
     Starting,
     Running,
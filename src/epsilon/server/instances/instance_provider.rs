@@ -1,22 +1,45 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use kube::api::DeleteParams;
 use serde_json::{Map, Value};
+use tokio::sync::{Mutex, RwLock};
 
-use crate::controller::definitions::epsilon_instance::EpsilonInstance;
+use crate::controller::definitions::epsilon_instance::{EpsilonInstance, VectorOfInstance};
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
 use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::server::instances::common::allocation::AllocationRequest;
+use crate::epsilon::server::instances::common::instance_query::{
+    InstanceFilter, InstancePage, InstanceQuery, InstanceSort,
+};
 use crate::epsilon::server::instances::common::instance_type::InstanceType;
 use crate::epsilon::server::instances::common::state::EpsilonState;
-use crate::EpsilonController;
+use crate::{EpsilonApi, EpsilonConfig, EpsilonController};
 
 pub struct InstanceProvider {
     epsilon_controller: Arc<EpsilonController>,
+    epsilon_api: Arc<EpsilonApi>,
+    allocation_lock: Mutex<()>,
+
+    reservations: RwLock<HashMap<String, HashMap<String, Instant>>>,
+    reservation_ttl: Duration,
 }
 
 impl InstanceProvider {
-    pub fn new(epsilon_controller: &Arc<EpsilonController>) -> InstanceProvider {
+    pub fn new(
+        epsilon_controller: &Arc<EpsilonController>,
+        epsilon_api: &Arc<EpsilonApi>,
+        config: &Arc<EpsilonConfig>,
+    ) -> InstanceProvider {
         Self {
             epsilon_controller: Arc::clone(epsilon_controller),
+            epsilon_api: Arc::clone(epsilon_api),
+            allocation_lock: Mutex::new(()),
+
+            reservations: RwLock::new(HashMap::new()),
+            reservation_ttl: Duration::from_secs(config.reservation.ttl),
         }
     }
 
@@ -24,13 +47,102 @@ impl InstanceProvider {
         &self,
         template_name: &str,
         content: Option<Value>,
+    ) -> Result<EpsilonInstance, EpsilonError> {
+        self.create_instance(template_name, content, false).await
+    }
+
+    // Created allocated, so no queue sends players to it before its owner does
+    pub async fn start_allocated_instance(
+        &self,
+        template_name: &str,
+        content: Option<Value>,
+    ) -> Result<EpsilonInstance, EpsilonError> {
+        self.create_instance(template_name, content, true).await
+    }
+
+    async fn create_instance(
+        &self,
+        template_name: &str,
+        content: Option<Value>,
+        allocated: bool,
     ) -> Result<EpsilonInstance, EpsilonError> {
         let default = Value::Object(Map::new());
 
-        Ok(self
+        let instance = self
+            .epsilon_controller
+            .create_epsilon_instance(
+                template_name,
+                content.unwrap_or(default),
+                BTreeMap::new(),
+                allocated,
+            )
+            .await?;
+
+        self.notify_created(&instance);
+
+        Ok(instance)
+    }
+
+    pub async fn allocate_instance(
+        &self,
+        request: &AllocationRequest,
+    ) -> Result<EpsilonInstance, EpsilonError> {
+        let _lock = self.allocation_lock.lock().await;
+
+        let instances = self
+            .get_instances(
+                Some(InstanceType::Server),
+                Some(&request.template),
+                Some(EpsilonState::Running),
+            )
+            .await?;
+
+        for instance in instances {
+            if instance.is_allocated()
+                || !instance.match_labels(&request.labels)
+                || !instance.match_content(&request.content)
+            {
+                continue;
+            }
+
+            match self.get_available_slots(&instance).await {
+                Ok(available_slots) if available_slots >= request.slots => {}
+                _ => continue,
+            }
+
+            if let Some(allocated_instance) = self
+                .epsilon_controller
+                .allocate_epsilon_instance(&instance.get_name())
+                .await?
+            {
+                info!(
+                    "An instance has been allocated (name={})",
+                    allocated_instance.get_name()
+                );
+
+                return Ok(allocated_instance);
+            }
+        }
+
+        let instance = self
             .epsilon_controller
-            .create_epsilon_instance(template_name, content.unwrap_or(default))
-            .await?)
+            .create_epsilon_instance(
+                &request.template,
+                Value::Object(request.content.clone()),
+                request.labels.clone(),
+                true,
+            )
+            .await?;
+
+        self.notify_created(&instance);
+
+        info!(
+            "No instance matches the allocation, an allocated one is starting (name={}, template={})",
+            instance.get_name(),
+            request.template
+        );
+
+        Ok(instance)
     }
 
     pub async fn remove_instance(&self, name: &str) -> Result<(), EpsilonError> {
@@ -40,18 +152,43 @@ impl InstanceProvider {
             .get_epsilon_instance_api()
             .delete(name, &DeleteParams::default())
             .await
-            .map_err(|_| EpsilonError::RemoveInstanceError(name.to_owned()))?;
+            .map_err(|error| match error {
+                kube::Error::Api(response) if response.code == 404 => {
+                    EpsilonError::InstanceNotFoundError(name.to_owned())
+                }
+                error => EpsilonError::RemoveInstanceError(name.to_owned(), error),
+            })?;
+
+        self.epsilon_api
+            .send(EpsilonEvent::InstanceRemoved {
+                instance: name.to_owned(),
+            })
+            .ok();
 
         Ok(())
     }
 
+    fn notify_created(&self, instance: &EpsilonInstance) {
+        self.epsilon_api
+            .send(EpsilonEvent::InstanceCreated {
+                instance: instance.get_name(),
+                template: instance.spec.template.clone(),
+            })
+            .ok();
+    }
+
     pub async fn get_instance(&self, instance_name: &str) -> Result<EpsilonInstance, EpsilonError> {
         Ok(self
             .epsilon_controller
             .get_epsilon_instance_api()
             .get(instance_name)
             .await
-            .map_err(|_| EpsilonError::RetrieveInstanceError)?)
+            .map_err(|error| match error {
+                kube::Error::Api(response) if response.code == 404 => {
+                    EpsilonError::InstanceNotFoundError(instance_name.to_owned())
+                }
+                error => EpsilonError::RetrieveInstanceError(Some(error)),
+            })?)
     }
 
     pub async fn get_instances(
@@ -59,6 +196,19 @@ impl InstanceProvider {
         type_option: Option<InstanceType>,
         template_option: Option<&str>,
         state_option: Option<EpsilonState>,
+    ) -> Result<Vec<Arc<EpsilonInstance>>, EpsilonError> {
+        self.get_filtered_instances(&InstanceFilter {
+            t: type_option,
+            template: template_option,
+            state: state_option,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn get_filtered_instances(
+        &self,
+        filter: &InstanceFilter<'_>,
     ) -> Result<Vec<Arc<EpsilonInstance>>, EpsilonError> {
         let instances = self.epsilon_controller.get_epsilon_instance_store().state();
 
@@ -66,7 +216,7 @@ impl InstanceProvider {
             instance
                 .status
                 .as_ref()
-                .ok_or(EpsilonError::RetrieveInstanceError)?;
+                .ok_or(EpsilonError::RetrieveInstanceError(None))?;
         }
 
         Ok(instances
@@ -74,29 +224,157 @@ impl InstanceProvider {
             .filter(|instance| {
                 let status = instance.status.as_ref().unwrap();
 
-                let condition1 = if let Some(template_name) = template_option {
+                let condition1 = if let Some(template_name) = filter.template {
                     instance.spec.template == template_name
                 } else {
                     true
                 };
 
-                let condition2 = if let Some(instance_type) = type_option {
+                let condition2 = if let Some(instance_type) = filter.t {
                     status.t == instance_type
                 } else {
                     true
                 };
 
-                let condition3 = if let Some(state) = state_option {
+                let condition3 = if let Some(state) = filter.state {
                     status.state == state
                 } else {
                     true
                 };
 
-                condition1 && condition2 && condition3
+                let condition4 = if let Some(hub) = filter.hub {
+                    status.hub == hub
+                } else {
+                    true
+                };
+
+                let condition5 = instance.match_labels(&filter.labels);
+
+                condition1 && condition2 && condition3 && condition4 && condition5
             })
             .collect())
     }
 
+    pub async fn list_instances(
+        &self,
+        query: &InstanceQuery,
+    ) -> Result<InstancePage, EpsilonError> {
+        let offset = query.get_offset()?;
+        let limit = query.get_limit();
+
+        let mut entries = Vec::new();
+
+        for instance in self.get_filtered_instances(&query.to_filter()?).await? {
+            let free_slots = instance.status.as_ref().unwrap().slots
+                - instance.get_cached_online_count()
+                - self.get_reserved_slots(&instance.get_name()).await;
+
+            if let Some(min_free_slots) = query.min_free_slots {
+                if free_slots < min_free_slots {
+                    continue;
+                }
+            }
+
+            entries.push((instance, free_slots));
+        }
+
+        entries.sort_by(|(instance1, free_slots1), (instance2, free_slots2)| {
+            let ordering = match query.sort.unwrap_or(InstanceSort::Name) {
+                InstanceSort::Name => Ordering::Equal,
+                InstanceSort::Template => instance1.spec.template.cmp(&instance2.spec.template),
+                InstanceSort::State => instance1.get_state().cmp(&instance2.get_state()),
+                InstanceSort::Online => instance1
+                    .get_cached_online_count()
+                    .cmp(&instance2.get_cached_online_count()),
+                InstanceSort::FreeSlots => free_slots1.cmp(free_slots2),
+            }
+            .then_with(|| instance1.get_name().cmp(&instance2.get_name()));
+
+            if query.desc.unwrap_or(false) {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let total = entries.len();
+
+        let page: Vec<Arc<EpsilonInstance>> = entries
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(instance, _)| instance)
+            .collect();
+
+        let next_offset = offset + page.len();
+
+        Ok(InstancePage {
+            instances: page.to_json().await?,
+
+            total,
+            next_cursor: if next_offset < total {
+                Some(next_offset.to_string())
+            } else {
+                None
+            },
+        })
+    }
+
+    pub async fn reserve_slots(&self, instance_name: &str, players: &[String]) {
+        let expire_at = Instant::now() + self.reservation_ttl;
+
+        let mut reservations = self.reservations.write().await;
+        let instance_reservations = reservations.entry(instance_name.to_owned()).or_default();
+
+        for player in players {
+            instance_reservations.insert(player.to_owned(), expire_at);
+        }
+    }
+
+    pub async fn release_slots(&self, instance_name: &str, players: &[String]) {
+        let mut reservations = self.reservations.write().await;
+
+        if let Some(instance_reservations) = reservations.get_mut(instance_name) {
+            for player in players {
+                instance_reservations.remove(player);
+            }
+
+            if instance_reservations.is_empty() {
+                reservations.remove(instance_name);
+            }
+        }
+    }
+
+    pub async fn get_reserved_slots(&self, instance_name: &str) -> i32 {
+        let mut reservations = self.reservations.write().await;
+
+        match reservations.get_mut(instance_name) {
+            None => 0,
+            Some(instance_reservations) => {
+                let now = Instant::now();
+
+                instance_reservations.retain(|_, expire_at| *expire_at > now);
+
+                let reserved_slots = instance_reservations.len() as i32;
+
+                if reserved_slots == 0 {
+                    reservations.remove(instance_name);
+                }
+
+                reserved_slots
+            }
+        }
+    }
+
+    pub async fn get_available_slots(
+        &self,
+        instance: &EpsilonInstance,
+    ) -> Result<i32, EpsilonError> {
+        let available_slots = instance.get_available_slots().await?;
+
+        Ok(available_slots - self.get_reserved_slots(&instance.get_name()).await)
+    }
+
     pub async fn enable_in_game_instance(&self, name: &str) -> Result<(), EpsilonError> {
         self.epsilon_controller.in_game_epsilon_instance(name).await
     }
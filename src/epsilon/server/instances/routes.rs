@@ -4,70 +4,114 @@ use rocket::State;
 use serde_json::json;
 use serde_json::Value;
 
-use crate::controller::definitions::epsilon_instance::InstanceJson;
+use crate::controller::definitions::epsilon_instance::VectorOfInstance;
+use crate::epsilon::auth::guards::{AdminAccess, ArrivalAccess, ReadAccess, ServerAccess};
 use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::server::instances::common::allocation::AllocationRequest;
 use crate::Context;
 
 use rocket::serde::json::Json;
 
+#[rocket_okapi::openapi(tag = "Instance")]
 #[rocket::post("/create/<template>", format = "json", data = "<content>")]
 pub async fn create(
     template: &str,
     content: Json<Value>,
+    _auth: AdminAccess,
     context: &State<Arc<Context>>,
 ) -> Result<String, EpsilonError> {
     let instance_provider = context.get_instance_provider();
 
     let instance = instance_provider
         .start_instance(template, Some(content.0))
-        .await
-        .map_err(|_| {
-            EpsilonError::ApiServerError(format!(
-                "Failed to create an instance from template ({})",
-                template
-            ))
-        })?;
+        .await?;
 
     info!("An instance has been created (template={})", template);
 
     Ok(instance.get_name())
 }
 
+#[rocket_okapi::openapi(tag = "Instance")]
+#[rocket::post("/allocate", format = "json", data = "<body>")]
+pub async fn allocate(
+    body: Json<AllocationRequest>,
+    _auth: AdminAccess,
+    context: &State<Arc<Context>>,
+) -> Result<String, EpsilonError> {
+    let instance_provider = context.get_instance_provider();
+
+    let instance = instance_provider.allocate_instance(&body).await?;
+
+    Ok(serde_json::to_string(&instance.to_allocation_json())
+        .map_err(|_| EpsilonError::ParseJsonError("Allocate Instance".to_owned()))?)
+}
+
+#[rocket_okapi::openapi(tag = "Instance")]
 #[rocket::post("/close/<instance>")]
-pub async fn close(instance: &str, context: &State<Arc<Context>>) -> Result<(), EpsilonError> {
+pub async fn close(
+    instance: &str,
+    auth: ServerAccess,
+    context: &State<Arc<Context>>,
+) -> Result<(), EpsilonError> {
     let instance_provider = context.get_instance_provider();
 
-    instance_provider
-        .remove_instance(instance)
-        .await
-        .map_err(|_| {
-            EpsilonError::ApiServerError(format!("Failed to close instance ({})", instance))
-        })?;
+    auth.check_instance(instance_provider, instance).await?;
+    instance_provider.remove_instance(instance).await?;
 
     info!("An instance has been closed (instance={})", instance);
 
     Ok(())
 }
 
+#[rocket_okapi::openapi(tag = "Instance")]
 #[rocket::post("/in_game/<instance>")]
-pub async fn in_game(instance: &str, context: &State<Arc<Context>>) -> Result<(), EpsilonError> {
+pub async fn in_game(
+    instance: &str,
+    auth: ServerAccess,
+    context: &State<Arc<Context>>,
+) -> Result<(), EpsilonError> {
     let instance_provider = context.get_instance_provider();
 
-    instance_provider
-        .enable_in_game_instance(instance)
-        .await
-        .map_err(|_| {
-            EpsilonError::ApiServerError(format!("Failed to set in game instance ({})", instance))
-        })?;
+    auth.check_instance(instance_provider, instance).await?;
+    instance_provider.enable_in_game_instance(instance).await?;
 
     info!("An instance is now in game (name={})", instance);
 
     Ok(())
 }
 
+#[rocket_okapi::openapi(tag = "Instance")]
+#[rocket::post("/confirm/<instance>", format = "json", data = "<players>")]
+pub async fn confirm(
+    instance: &str,
+    players: Json<Vec<String>>,
+    auth: ArrivalAccess,
+    context: &State<Arc<Context>>,
+) -> Result<(), EpsilonError> {
+    let instance_provider = context.get_instance_provider();
+
+    auth.check_instance(instance_provider, instance).await?;
+    instance_provider.release_slots(instance, &players).await;
+
+    context
+        .get_transfer_provider()
+        .confirm_arrival(instance, &players)
+        .await;
+
+    info!(
+        "Player {} arrived on instance (name={})",
+        players.join("/"),
+        instance
+    );
+
+    Ok(())
+}
+
+#[rocket_okapi::openapi(tag = "Instance")]
 #[rocket::get("/get/<instance_name>")]
 pub async fn get(
     instance_name: &str,
+    _auth: ReadAccess,
     context: &State<Arc<Context>>,
 ) -> Result<String, EpsilonError> {
     let instance_provider = context.get_instance_provider();
@@ -77,50 +121,45 @@ pub async fn get(
         .map_err(|_| EpsilonError::ParseJsonError("Get Instance".to_owned()))?)
 }
 
-#[rocket::get("/get_all")]
-pub async fn get_all(context: &State<Arc<Context>>) -> Result<String, EpsilonError> {
+#[rocket_okapi::openapi(tag = "Instance")]
+#[rocket::get("/<instance_name>/info", rank = 2)]
+pub async fn info(
+    instance_name: &str,
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> Result<String, EpsilonError> {
     let instance_provider = context.get_instance_provider();
+    let instance = instance_provider.get_instance(instance_name).await?;
 
-    let instances = instance_provider
-        .get_instances(None, None, None)
-        .await
-        .map_err(|_| EpsilonError::ApiServerError("Failed to get every instance".to_string()))?
-        .into_iter();
+    Ok(serde_json::to_string(&instance.get_server_info().await?)
+        .map_err(|_| EpsilonError::ParseJsonError("Get Instance Info".to_owned()))?)
+}
 
-    let mut json_array: Vec<InstanceJson> = Vec::new();
+#[rocket_okapi::openapi(tag = "Instance")]
+#[rocket::get("/get_all")]
+pub async fn get_all(
+    _auth: ReadAccess,
+    context: &State<Arc<Context>>,
+) -> Result<String, EpsilonError> {
+    let instance_provider = context.get_instance_provider();
 
-    for instance in instances {
-        let json = instance.to_json().await?;
-        json_array.push(json);
-    }
+    let instances = instance_provider.get_instances(None, None, None).await?;
 
-    Ok(json!({ "instances": json_array }).to_string())
+    Ok(json!({ "instances": instances.to_json().await? }).to_string())
 }
 
+#[rocket_okapi::openapi(tag = "Instance")]
 #[rocket::get("/get_from_template/<template>")]
 pub async fn get_from_template(
     template: &str,
+    _auth: ReadAccess,
     context: &State<Arc<Context>>,
 ) -> Result<String, EpsilonError> {
     let instance_provider = context.get_instance_provider();
 
     let instances = instance_provider
         .get_instances(None, Some(template), None)
-        .await
-        .map_err(|_| {
-            EpsilonError::ApiServerError(format!(
-                "Failed to get instance from template {}",
-                template
-            ))
-        })?
-        .into_iter();
-
-    let mut json_array: Vec<InstanceJson> = Vec::with_capacity(instances.len());
-
-    for instance in instances {
-        let json = instance.to_json().await?;
-        json_array.push(json);
-    }
-
-    Ok(json!({ "instances": json_array }).to_string())
+        .await?;
+
+    Ok(json!({ "instances": instances.to_json().await? }).to_string())
 }
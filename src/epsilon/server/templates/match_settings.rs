@@ -0,0 +1,35 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatchSettings {
+    pub min_players: i32,
+    pub max_players: Option<i32>,
+
+    #[serde(default)]
+    pub countdown: u64,
+
+    pub timeout: Option<u64>,
+    pub fallback_min_players: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<RatingSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teams: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RatingSettings {
+    // Allowed rating spread of a match, it widens by `widening` every second the oldest group waits
+    pub window: f64,
+    #[serde(default)]
+    pub widening: f64,
+    pub max_window: Option<f64>,
+
+    #[serde(default = "default_rating")]
+    pub default_rating: f64,
+}
+
+fn default_rating() -> f64 {
+    1000.0
+}
@@ -1,4 +1,5 @@
 pub mod template_provider;
 
+pub mod match_settings;
 pub mod resources;
 pub mod template;
@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::epsilon::server::instances::common::instance_type::InstanceType;
+use crate::epsilon::server::templates::match_settings::MatchSettings;
 use crate::epsilon::server::templates::resources::Resources;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Template {
     pub name: String,
     pub parent: String,
@@ -18,4 +20,7 @@ pub struct Template {
     pub resources: Resources,
 
     pub labels: HashMap<String, Value>,
+
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub match_settings: Option<MatchSettings>,
 }
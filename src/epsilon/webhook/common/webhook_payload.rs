@@ -0,0 +1,30 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::epsilon::api::common::epsilon_events::SequencedEvent;
+
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    pub id: u64,
+    pub event: String,
+    pub data: Value,
+}
+
+impl WebhookPayload {
+    pub fn new(sequenced_event: &SequencedEvent) -> Self {
+        Self {
+            id: sequenced_event.id,
+            event: sequenced_event.event.to_string(),
+            data: sequenced_event.event.to_data(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeadLetter<'a> {
+    pub url: &'a str,
+    pub attempts: u32,
+    pub error: String,
+
+    pub payload: &'a WebhookPayload,
+}
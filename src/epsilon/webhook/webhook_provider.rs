@@ -0,0 +1,500 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use sha2::Sha256;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+
+use crate::config::WebhookSubscription;
+use crate::epsilon::api::common::epsilon_events::SequencedEvent;
+use crate::epsilon::api::common::event_filter::EventFilter;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::webhook::common::webhook_payload::{DeadLetter, WebhookPayload};
+use crate::{EpsilonApi, EpsilonConfig};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct WebhookProvider {
+    client: Client,
+
+    attempts: u32,
+    backoff: Duration,
+    dead_letter: String,
+    max_pending: usize,
+}
+
+impl WebhookProvider {
+    pub fn start(
+        epsilon_api: &Arc<EpsilonApi>,
+        config: &Arc<EpsilonConfig>,
+    ) -> Result<(), EpsilonError> {
+        let webhook_config = &config.webhook;
+
+        let webhook_provider = Arc::new(Self {
+            client: Client::builder().timeout(Duration::from_secs(10)).build()?,
+
+            attempts: webhook_config.attempts.max(1),
+            backoff: Duration::from_millis(webhook_config.backoff),
+            dead_letter: webhook_config.dead_letter.clone(),
+            max_pending: webhook_config.max_pending.max(1),
+        });
+
+        for subscription in &webhook_config.subscriptions {
+            let filter = EventFilter {
+                t: subscription.events.clone(),
+                ..Default::default()
+            };
+
+            filter.validate()?;
+
+            info!("Webhook subscription started (url={})", subscription.url);
+
+            tokio::spawn(Arc::clone(&webhook_provider).listen(
+                Arc::clone(epsilon_api),
+                Arc::new(subscription.clone()),
+                filter,
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn listen(
+        self: Arc<Self>,
+        epsilon_api: Arc<EpsilonApi>,
+        subscription: Arc<WebhookSubscription>,
+        filter: EventFilter,
+    ) {
+        let pending = Arc::new(Semaphore::new(self.max_pending));
+
+        let (replay, mut rx) = epsilon_api.subscribe(None);
+        let mut last_id = replay.last_id;
+
+        loop {
+            let sequenced_events = match rx.recv().await {
+                Ok(sequenced_event) => vec![sequenced_event],
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => epsilon_api.replay(last_id).events,
+            };
+
+            for sequenced_event in sequenced_events {
+                if sequenced_event.id <= last_id {
+                    continue;
+                }
+
+                last_id = sequenced_event.id;
+
+                if filter.matches(&sequenced_event.event) {
+                    self.dispatch(&subscription, &pending, &sequenced_event);
+                }
+            }
+        }
+    }
+
+    // Each delivery retries in its own task, so a failing endpoint does not hold back the next events
+    fn dispatch(
+        self: &Arc<Self>,
+        subscription: &Arc<WebhookSubscription>,
+        pending: &Arc<Semaphore>,
+        sequenced_event: &SequencedEvent,
+    ) {
+        let webhook_provider = Arc::clone(self);
+        let subscription = Arc::clone(subscription);
+        let payload = WebhookPayload::new(sequenced_event);
+
+        match Arc::clone(pending).try_acquire_owned() {
+            Ok(permit) => {
+                tokio::spawn(async move {
+                    webhook_provider.deliver(&subscription, &payload).await;
+                    drop(permit);
+                });
+            }
+            Err(_) => {
+                let error = EpsilonError::SendEventError(format!(
+                    "{} deliveries are already pending",
+                    self.max_pending
+                ));
+
+                tokio::spawn(async move {
+                    webhook_provider
+                        .write_dead_letter(&subscription, &payload, 0, error)
+                        .await;
+                });
+            }
+        }
+    }
+
+    async fn deliver(&self, subscription: &WebhookSubscription, payload: &WebhookPayload) {
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(error) => {
+                error!(
+                    "Failed to serialize webhook payload (id={}): {}",
+                    payload.id, error
+                );
+                return;
+            }
+        };
+
+        let mut attempt = 1;
+
+        loop {
+            let error = match self.post(subscription, payload, &body).await {
+                Ok(()) => return,
+                Err(error) => error,
+            };
+
+            if attempt >= self.attempts {
+                self.write_dead_letter(subscription, payload, attempt, error)
+                    .await;
+                return;
+            }
+
+            let delay = self.backoff * 2u32.pow((attempt - 1).min(10));
+
+            debug!(
+                "Webhook delivery failed, retry in {:?} (url={}, id={}): {}",
+                delay, subscription.url, payload.id, error
+            );
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn post(
+        &self,
+        subscription: &WebhookSubscription,
+        payload: &WebhookPayload,
+        body: &[u8],
+    ) -> Result<(), EpsilonError> {
+        let mut request = self
+            .client
+            .post(&subscription.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Epsilon-Event", &payload.event)
+            .header("X-Epsilon-Delivery", payload.id.to_string())
+            .body(body.to_vec());
+
+        if let Some(secret) = &subscription.secret {
+            request = request.header(
+                "X-Epsilon-Signature",
+                format!("sha256={}", Self::sign(secret, body)),
+            );
+        }
+
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn write_dead_letter(
+        &self,
+        subscription: &WebhookSubscription,
+        payload: &WebhookPayload,
+        attempts: u32,
+        error: EpsilonError,
+    ) {
+        warn!(
+            "Webhook delivery abandoned after {} attempts (url={}, id={}): {}",
+            attempts, subscription.url, payload.id, error
+        );
+
+        let dead_letter = DeadLetter {
+            url: &subscription.url,
+            attempts,
+            error: error.to_string(),
+
+            payload,
+        };
+
+        let mut line = match serde_json::to_string(&dead_letter) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        line.push('\n');
+
+        let result = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dead_letter)
+            .await
+        {
+            Ok(mut file) => file.write_all(line.as_bytes()).await,
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            error!(
+                "Failed to write webhook dead letter (file={}): {}",
+                self.dead_letter, error
+            );
+        }
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+
+        mac.update(body);
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use reqwest::Client;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+    use tokio::sync::Semaphore;
+    use tokio::time::sleep;
+
+    use super::WebhookProvider;
+    use crate::config::WebhookSubscription;
+    use crate::epsilon::api::common::epsilon_events::{EpsilonEvent, SequencedEvent};
+    use crate::epsilon::webhook::common::webhook_payload::WebhookPayload;
+
+    struct ReceivedRequest {
+        headers: String,
+        body: Vec<u8>,
+    }
+
+    impl ReceivedRequest {
+        fn get_header(&self, name: &str) -> Option<&str> {
+            self.headers.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+
+                if key.eq_ignore_ascii_case(name) {
+                    Some(value.trim())
+                } else {
+                    None
+                }
+            })
+        }
+    }
+
+    // Local HTTP endpoint answering with the given statuses in order, then repeating the last one
+    async fn start_endpoint(statuses: Vec<u16>) -> (String, UnboundedReceiver<ReceivedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+
+        let (tx, rx) = unbounded_channel();
+
+        tokio::spawn(async move {
+            for index in 0.. {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(_) => return,
+                };
+
+                let request = read_request(&mut stream).await;
+                let status = statuses[index.min(statuses.len() - 1)];
+
+                let response = format!(
+                    "HTTP/1.1 {} Endpoint\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    status
+                );
+
+                tx.send(request).ok();
+                stream.write_all(response.as_bytes()).await.ok();
+            }
+        });
+
+        (url, rx)
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> ReceivedRequest {
+        let mut buffer = Vec::new();
+        let mut chunk = [0; 4096];
+
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "connection closed before the end of the request");
+            buffer.extend_from_slice(&chunk[..read]);
+
+            let header_end = match buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                Some(position) => position + 4,
+                None => continue,
+            };
+
+            let headers = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+
+            let request = ReceivedRequest {
+                headers,
+                body: Vec::new(),
+            };
+
+            let length: usize = request
+                .get_header("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+
+            while buffer.len() < header_end + length {
+                let read = stream.read(&mut chunk).await.unwrap();
+                assert!(read > 0, "connection closed before the end of the body");
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+
+            return ReceivedRequest {
+                body: buffer[header_end..header_end + length].to_vec(),
+                ..request
+            };
+        }
+    }
+
+    fn get_dead_letter(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "epsilon-webhook-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+
+        std::fs::remove_file(&path).ok();
+
+        path
+    }
+
+    fn create_provider(attempts: u32, dead_letter: &Path) -> Arc<WebhookProvider> {
+        Arc::new(WebhookProvider {
+            client: Client::new(),
+
+            attempts,
+            backoff: Duration::from_millis(1),
+            dead_letter: dead_letter.to_string_lossy().into_owned(),
+            max_pending: 1,
+        })
+    }
+
+    fn create_subscription(url: String) -> Arc<WebhookSubscription> {
+        Arc::new(WebhookSubscription {
+            url,
+            events: Vec::new(),
+            secret: Some(String::from("secret")),
+        })
+    }
+
+    fn create_event() -> SequencedEvent {
+        SequencedEvent {
+            id: 7,
+            event: EpsilonEvent::InstanceRemoved {
+                instance: String::from("lobby-1"),
+            },
+        }
+    }
+
+    async fn read_dead_letters(path: &Path) -> Vec<Value> {
+        for _ in 0..100 {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                return content
+                    .lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect();
+            }
+
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        Vec::new()
+    }
+
+    #[test]
+    fn sign_uses_hmac_sha256() {
+        assert_eq!(
+            WebhookProvider::sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[tokio::test]
+    async fn delivery_is_signed_and_retried() {
+        let (url, mut rx) = start_endpoint(vec![500, 200]).await;
+        let dead_letter = get_dead_letter("retried");
+
+        let webhook_provider = create_provider(3, &dead_letter);
+        let subscription = create_subscription(url);
+
+        webhook_provider
+            .deliver(&subscription, &WebhookPayload::new(&create_event()))
+            .await;
+
+        let first = rx.recv().await.unwrap();
+        let second = rx.recv().await.unwrap();
+
+        assert_eq!(first.body, second.body);
+        assert_eq!(
+            second.get_header("x-epsilon-event"),
+            Some("InstanceRemoved")
+        );
+        assert_eq!(second.get_header("x-epsilon-delivery"), Some("7"));
+        assert_eq!(
+            second.get_header("x-epsilon-signature"),
+            Some(format!("sha256={}", WebhookProvider::sign("secret", &second.body)).as_str())
+        );
+
+        let body: Value = serde_json::from_slice(&second.body).unwrap();
+        assert_eq!(body["id"], json!(7));
+        assert_eq!(body["data"]["instance"], json!("lobby-1"));
+
+        assert!(rx.try_recv().is_err());
+        assert!(!dead_letter.exists());
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_is_dead_lettered() {
+        let (url, mut rx) = start_endpoint(vec![503]).await;
+        let dead_letter = get_dead_letter("failed");
+
+        let webhook_provider = create_provider(2, &dead_letter);
+        let subscription = create_subscription(url.clone());
+
+        webhook_provider
+            .deliver(&subscription, &WebhookPayload::new(&create_event()))
+            .await;
+
+        assert!(rx.recv().await.is_some());
+        assert!(rx.recv().await.is_some());
+        assert!(rx.try_recv().is_err());
+
+        let dead_letters = read_dead_letters(&dead_letter).await;
+        std::fs::remove_file(&dead_letter).ok();
+
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0]["url"], json!(url));
+        assert_eq!(dead_letters[0]["attempts"], json!(2));
+        assert_eq!(dead_letters[0]["payload"]["id"], json!(7));
+    }
+
+    #[tokio::test]
+    async fn delivery_is_dead_lettered_when_too_many_are_pending() {
+        let (url, mut rx) = start_endpoint(vec![200]).await;
+        let dead_letter = get_dead_letter("pending");
+
+        let webhook_provider = create_provider(1, &dead_letter);
+        let subscription = create_subscription(url);
+
+        let pending = Arc::new(Semaphore::new(1));
+        let _permit = Arc::clone(&pending).try_acquire_owned().unwrap();
+
+        webhook_provider.dispatch(&subscription, &pending, &create_event());
+
+        let dead_letters = read_dead_letters(&dead_letter).await;
+        std::fs::remove_file(&dead_letter).ok();
+
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0]["attempts"], json!(0));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -10,6 +10,8 @@ use env_logger::fmt::Color;
 use k8s_openapi::chrono::Local;
 use kube::CustomResourceExt;
 use log::Level;
+use rocket_okapi::openapi_get_routes_spec;
+use rocket_okapi::settings::OpenApiSettings;
 use tokio::time::sleep;
 
 use crate::config::EpsilonConfig;
@@ -18,15 +20,25 @@ use crate::controller::definitions::epsilon_instance::EpsilonInstance;
 use crate::controller::definitions::epsilon_queue::EpsilonQueue;
 use crate::controller::epsilon_controller::EpsilonController;
 use crate::epsilon::api::epsilon_api::EpsilonApi;
+use crate::epsilon::api::openapi::EpsilonOpenApi;
+use crate::epsilon::auth::auth_provider::AuthProvider;
 use crate::epsilon::queue::queue_provider::QueueProvider;
+use crate::epsilon::queue::store::queue_store::create_queue_store;
+use crate::epsilon::queue::transfer_provider::TransferProvider;
 use crate::epsilon::server::instances::instance_provider::InstanceProvider;
 use crate::epsilon::server::instances::EResult;
 use crate::epsilon::server::templates::template_provider::TemplateProvider;
+use crate::epsilon::webhook::webhook_provider::WebhookProvider;
 use crate::tasks::hub_task::HubTask;
+use crate::tasks::match_task::MatchTask;
+use crate::tasks::persistence_task::PersistenceTask;
+use crate::tasks::position_task::PositionTask;
 use crate::tasks::proxy_task::ProxyTask;
 use crate::tasks::queue_task::QueueTask;
 use crate::tasks::task::Task;
 use crate::tasks::task_builder::TaskBuilder;
+use crate::tasks::template_task::TemplateTask;
+use crate::tasks::transfer_task::TransferTask;
 
 pub mod controller;
 
@@ -107,20 +119,37 @@ async fn main() -> EResult<()> {
 
     let config = EpsilonConfig::load("./config.json");
 
-    let epsilon_api = EpsilonApi::new();
+    let epsilon_api = EpsilonApi::new(&config);
+
+    WebhookProvider::start(&epsilon_api, &config)?;
 
     let template_provider = TemplateProvider::new(&config);
 
-    let controller = EpsilonController::new(&namespace, &template_provider).await;
-    let instance_provider = InstanceProvider::new(&controller);
+    let controller = EpsilonController::new(&namespace, &template_provider, &epsilon_api).await;
+    let instance_provider = InstanceProvider::new(&controller, &epsilon_api, &config);
+
+    let queue_store = create_queue_store(&config, &namespace).await?;
+
+    let queue_provider = QueueProvider::new(
+        &instance_provider,
+        &template_provider,
+        &epsilon_api,
+        &config,
+        queue_store,
+    )
+    .await?;
+
+    let transfer_provider = TransferProvider::new(&epsilon_api, &config);
 
-    let queue_provider = QueueProvider::new(&instance_provider, &template_provider).await?;
+    let auth_provider = AuthProvider::new(&config, &controller).await?;
 
     let context = Context::new(
         epsilon_api,
         template_provider,
         instance_provider,
         queue_provider,
+        transfer_provider,
+        auth_provider,
     );
 
     info!("Instance provider has been started");
@@ -130,7 +159,18 @@ async fn main() -> EResult<()> {
     TaskBuilder::new()
         .ignite_task(ProxyTask::init(Arc::clone(&context)).await?, 6000)
         .ignite_task(HubTask::init(Arc::clone(&context)).await?, 2000)
-        .ignite_task(QueueTask::init(Arc::clone(&context)).await?, 2000);
+        .ignite_task(QueueTask::init(Arc::clone(&context)).await?, 2000)
+        .ignite_task(MatchTask::init(Arc::clone(&context)).await?, 2000)
+        .ignite_task(TemplateTask::init(Arc::clone(&context)).await?, 10000)
+        .ignite_task(TransferTask::init(Arc::clone(&context)).await?, 1000)
+        .ignite_task(
+            PersistenceTask::init(Arc::clone(&context)).await?,
+            config.queue.persistence.flush_interval.max(100),
+        )
+        .ignite_task(
+            PositionTask::init(Arc::clone(&context)).await?,
+            config.queue.position_interval.max(1) * 1000,
+        );
 
     info!("Tasks have been started");
 
@@ -138,22 +178,112 @@ async fn main() -> EResult<()> {
         .merge(("ident", "Epsilon"))
         .merge(("address", "0.0.0.0"));
 
-    let _ = rocket::custom(figment)
+    let openapi_settings = OpenApiSettings::default();
+    let mut openapi = EpsilonOpenApi::new();
+
+    let mut building_rocket = rocket::custom(figment)
         .manage(Arc::clone(&context))
-        .mount("/", rocket::routes![epsilon::api::routes::ping])
-        .mount("/api", rocket::routes![epsilon::api::routes::events])
-        .mount("/queue", rocket::routes![epsilon::queue::routes::push])
-        .mount(
-            "/instance",
-            rocket::routes![
-                epsilon::server::instances::routes::create,
-                epsilon::server::instances::routes::close,
-                epsilon::server::instances::routes::in_game,
-                epsilon::server::instances::routes::get,
-                epsilon::server::instances::routes::get_all,
-                epsilon::server::instances::routes::get_from_template
-            ],
-        )
+        .register(
+            "/",
+            rocket::catchers![epsilon::epsilon_error::default_catcher],
+        );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::api::routes::ping,
+            epsilon::api::routes::metrics
+        ],
+    );
+
+    building_rocket = openapi.mount_events(
+        building_rocket,
+        "/api",
+        rocket::routes![epsilon::api::routes::events],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/queue",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::queue::routes::push,
+            epsilon::queue::routes::acknowledge,
+            epsilon::queue::routes::leave,
+            epsilon::queue::routes::offline,
+            epsilon::queue::routes::get,
+            epsilon::queue::routes::stats,
+            epsilon::queue::routes::player
+        ],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/instance",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::server::instances::routes::create,
+            epsilon::server::instances::routes::allocate,
+            epsilon::server::instances::routes::close,
+            epsilon::server::instances::routes::in_game,
+            epsilon::server::instances::routes::confirm,
+            epsilon::server::instances::routes::get,
+            epsilon::server::instances::routes::info,
+            epsilon::server::instances::routes::get_all,
+            epsilon::server::instances::routes::get_from_template
+        ],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/v2/instances",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::api::v2::instances::list,
+            epsilon::api::v2::instances::get,
+            epsilon::api::v2::instances::info,
+            epsilon::api::v2::instances::create,
+            epsilon::api::v2::instances::allocate,
+            epsilon::api::v2::instances::close,
+            epsilon::api::v2::instances::in_game,
+            epsilon::api::v2::instances::confirm
+        ],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/v2/proxies",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::api::v2::proxies::list,
+            epsilon::api::v2::proxies::get
+        ],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/v2/templates",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::api::v2::templates::list,
+            epsilon::api::v2::templates::get
+        ],
+    );
+
+    building_rocket = openapi.mount(
+        building_rocket,
+        "/v2/queues",
+        openapi_get_routes_spec![
+            openapi_settings: epsilon::api::v2::queues::list,
+            epsilon::api::v2::queues::get,
+            epsilon::api::v2::queues::details,
+            epsilon::api::v2::queues::stats,
+            epsilon::api::v2::queues::player,
+            epsilon::api::v2::queues::push,
+            epsilon::api::v2::queues::leave,
+            epsilon::api::v2::queues::offline,
+            epsilon::api::v2::queues::acknowledge
+        ],
+    );
+
+    let _ = openapi
+        .attach(building_rocket, &openapi_settings)
         .launch()
         .await?;
 
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::common::matchmaking::{balance_teams, RatingWindow};
+use crate::epsilon::server::instances::common::state::EpsilonState;
+use crate::epsilon::server::templates::match_settings::MatchSettings;
+use crate::{Context, Task};
+
+pub struct MatchTask {
+    context: Arc<Context>,
+
+    countdowns: HashMap<String, Instant>,
+}
+
+impl MatchTask {
+    async fn check_pending_matches(&mut self) -> Result<(), EpsilonError> {
+        let queue_provider = self.context.get_queue_provider();
+        let instance_provider = self.context.get_instance_provider();
+        let transfer_provider = self.context.get_transfer_provider();
+
+        for (match_id, instance_name, timed_out) in queue_provider.get_pending_matches().await {
+            let instance_name = match instance_name {
+                Some(instance_name) => instance_name,
+                None if timed_out => {
+                    queue_provider.cancel_match(match_id).await?;
+                    continue;
+                }
+                None => continue,
+            };
+
+            let state = match instance_provider.get_instance(&instance_name).await {
+                Ok(instance) => Some(instance.get_state()),
+                Err(EpsilonError::InstanceNotFoundError(_)) => None,
+                Err(error) => return Err(error),
+            };
+
+            match state {
+                Some(EpsilonState::Running) => {
+                    let pending_match = match queue_provider.take_match(match_id).await {
+                        Some(pending_match) => pending_match,
+                        None => continue,
+                    };
+
+                    if pending_match.groups.is_empty() {
+                        info!(
+                            "Every player left the match before it started (queue={}, instance={})",
+                            pending_match.queue, instance_name
+                        );
+
+                        instance_provider.remove_instance(&instance_name).await.ok();
+                        continue;
+                    }
+
+                    info!(
+                        "Match is starting (queue={}, instance={})",
+                        pending_match.queue, instance_name
+                    );
+
+                    for group in pending_match.groups {
+                        let players = group.players.clone();
+
+                        transfer_provider.transfer(group, &instance_name).await;
+
+                        instance_provider
+                            .reserve_slots(&instance_name, &players)
+                            .await;
+                    }
+                }
+                Some(EpsilonState::Starting) if !timed_out => {}
+                _ => {
+                    warn!(
+                        "Match instance did not start, players go back to queue (instance={})",
+                        instance_name
+                    );
+
+                    if state.is_some() {
+                        instance_provider.remove_instance(&instance_name).await.ok();
+                    }
+
+                    queue_provider.cancel_match(match_id).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn form_match(
+        &mut self,
+        queue_name: &str,
+        settings: &MatchSettings,
+        max_players: i32,
+        min_players: i32,
+        longest_wait: Option<Duration>,
+    ) -> Result<bool, EpsilonError> {
+        let queue_provider = self.context.get_queue_provider();
+        let instance_provider = self.context.get_instance_provider();
+
+        let mut window = settings
+            .rating
+            .as_ref()
+            .map(|rating| RatingWindow::new(rating, longest_wait.unwrap_or_default()));
+
+        let mut roster_size = 0;
+
+        let roster = queue_provider
+            .pop_roster(queue_name, min_players.max(1) as usize, |group| {
+                let group_size = group.players.len() as i32;
+
+                if roster_size + group_size > max_players {
+                    return false;
+                }
+
+                if let Some(window) = &mut window {
+                    if !window.accept(group) {
+                        return false;
+                    }
+                }
+
+                roster_size += group_size;
+
+                true
+            })
+            .await?;
+
+        let (match_id, groups) = match roster {
+            Some(roster) => roster,
+            None => return Ok(false),
+        };
+
+        let teams = match settings.teams {
+            Some(team_count) if team_count > 1 => {
+                let default_rating = settings
+                    .rating
+                    .as_ref()
+                    .map_or(0.0, |rating| rating.default_rating);
+
+                balance_teams(&groups, team_count, default_rating)
+            }
+            _ => Vec::new(),
+        };
+
+        let players: Vec<&String> = groups.iter().flat_map(|group| &group.players).collect();
+        let content = json!({
+            "match": { "players": players, "groups": groups, "teams": teams }
+        });
+
+        let started = match queue_provider.next_template(queue_name).await {
+            Ok(template_name) => {
+                instance_provider
+                    .start_allocated_instance(&template_name, Some(content))
+                    .await
+            }
+            Err(error) => Err(error),
+        };
+
+        let instance = match started {
+            Ok(instance) => instance,
+            Err(error) => {
+                queue_provider.cancel_match(match_id).await?;
+
+                return Err(error);
+            }
+        };
+
+        let instance_name = instance.get_name();
+
+        info!(
+            "Match formed (queue={}, instance={}, players={})",
+            queue_name, instance_name, roster_size
+        );
+
+        queue_provider
+            .set_match_instance(match_id, &instance_name)
+            .await;
+
+        self.context
+            .get_epsilon_api()
+            .send(EpsilonEvent::MatchFormed {
+                queue: queue_name.to_owned(),
+                instance: instance_name,
+                groups,
+                teams,
+            })
+            .ok();
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Task for MatchTask {
+    async fn init(context: Arc<Context>) -> Result<Box<dyn Task>, EpsilonError> {
+        Ok(Box::new(Self {
+            context,
+
+            countdowns: HashMap::new(),
+        }))
+    }
+
+    async fn run(&mut self) -> Result<(), EpsilonError> {
+        self.check_pending_matches().await?;
+
+        let context = Arc::clone(&self.context);
+        let now = Instant::now();
+
+        for (queue_name, queue) in context.get_queue_provider().get_queues() {
+            let (settings, max_players, player_count, longest_wait) = {
+                let queue = queue.read().await;
+
+                let settings = match queue.get_match_settings() {
+                    Some(settings) => settings.clone(),
+                    None => continue,
+                };
+
+                let max_players = settings
+                    .max_players
+                    .unwrap_or(queue.get_max_group_size() as i32);
+
+                (
+                    settings,
+                    max_players,
+                    queue.get_player_count() as i32,
+                    queue.get_longest_wait(),
+                )
+            };
+
+            let ready = if player_count >= settings.min_players.max(1) {
+                let countdown_start = *self.countdowns.entry(queue_name.clone()).or_insert(now);
+
+                player_count >= max_players
+                    || now.duration_since(countdown_start)
+                        >= Duration::from_secs(settings.countdown)
+            } else {
+                self.countdowns.remove(queue_name);
+
+                match (
+                    settings.timeout,
+                    settings.fallback_min_players,
+                    longest_wait,
+                ) {
+                    (Some(timeout), Some(fallback_min_players), Some(longest_wait)) => {
+                        longest_wait >= Duration::from_secs(timeout)
+                            && player_count >= fallback_min_players.max(1)
+                    }
+                    _ => false,
+                }
+            };
+
+            if ready {
+                let min_players = if player_count >= settings.min_players {
+                    settings.min_players
+                } else {
+                    settings
+                        .fallback_min_players
+                        .unwrap_or(settings.min_players)
+                };
+
+                // A rated queue may not find a roster within its window yet, it widens next run
+                if self
+                    .form_match(
+                        queue_name,
+                        &settings,
+                        max_players,
+                        min_players,
+                        longest_wait,
+                    )
+                    .await?
+                {
+                    self.countdowns.remove(queue_name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Match:Task, form matches when enough players are queued"
+    }
+}
@@ -1,5 +1,10 @@
 pub mod hub_task;
+pub mod match_task;
+pub mod persistence_task;
+pub mod position_task;
 pub mod proxy_task;
 pub mod queue_task;
 pub mod task;
 pub mod task_builder;
+pub mod template_task;
+pub mod transfer_task;
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::{Context, Task};
+
+pub struct PersistenceTask {
+    context: Arc<Context>,
+}
+
+#[async_trait]
+impl Task for PersistenceTask {
+    async fn init(context: Arc<Context>) -> Result<Box<dyn Task>, EpsilonError> {
+        Ok(Box::new(Self { context }))
+    }
+
+    async fn run(&mut self) -> Result<(), EpsilonError> {
+        self.context.get_queue_provider().flush().await;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Persistence:Task, write the queue operations to the queue store"
+    }
+}
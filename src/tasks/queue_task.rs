@@ -1,10 +1,12 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 
-use crate::controller::definitions::epsilon_instance::VectorOfInstance;
-use crate::epsilon::api::common::epsilon_events::EpsilonEvent::SendToServer;
+use crate::controller::definitions::epsilon_instance::EpsilonInstance;
 use crate::epsilon::epsilon_error::EpsilonError;
+use crate::epsilon::queue::common::group::JoinTarget;
+use crate::epsilon::queue::common::placement::{place_on, Candidate};
 use crate::epsilon::server::instances::common::instance_type::InstanceType;
 use crate::epsilon::server::instances::common::state::EpsilonState;
 use crate::{Context, Task};
@@ -13,6 +15,58 @@ pub struct QueueTask {
     context: Arc<Context>,
 }
 
+impl QueueTask {
+    // Instance each target points to, players are found from their last transfer, then from
+    // the player sample of the ready instances
+    async fn resolve_targets(
+        &self,
+        targets: Vec<JoinTarget>,
+        instances_ready: &[Arc<EpsilonInstance>],
+    ) -> HashMap<JoinTarget, String> {
+        let transfer_provider = self.context.get_transfer_provider();
+
+        let mut resolved = HashMap::new();
+        let mut samples: Option<Vec<(String, Vec<String>)>> = None;
+
+        for target in targets {
+            let instance_name = match &target {
+                JoinTarget::Instance(instance_name) => Some(instance_name.to_owned()),
+                JoinTarget::Player(player) => {
+                    match transfer_provider.get_player_location(player).await {
+                        Some(server) => Some(server),
+                        None => {
+                            if samples.is_none() {
+                                let mut fetched = Vec::with_capacity(instances_ready.len());
+
+                                for instance in instances_ready {
+                                    if let Ok(info) = instance.get_server_info().await {
+                                        fetched.push((instance.get_name(), info.players));
+                                    }
+                                }
+
+                                samples = Some(fetched);
+                            }
+
+                            samples
+                                .iter()
+                                .flatten()
+                                .find_map(|(instance_name, players)| {
+                                    players.contains(player).then(|| instance_name.to_owned())
+                                })
+                        }
+                    }
+                }
+            };
+
+            if let Some(instance_name) = instance_name {
+                resolved.insert(target, instance_name);
+            }
+        }
+
+        resolved
+    }
+}
+
 #[async_trait]
 impl Task for QueueTask {
     async fn init(context: Arc<Context>) -> Result<Box<dyn Task>, EpsilonError> {
@@ -20,62 +74,132 @@ impl Task for QueueTask {
     }
 
     async fn run(&mut self) -> Result<(), EpsilonError> {
-        let epsilon_api = self.context.get_epsilon_api();
         let instance_provider = self.context.get_instance_provider();
         let queue_provider = self.context.get_queue_provider();
+        let transfer_provider = self.context.get_transfer_provider();
 
-        for (template_name, queue) in queue_provider.get_queues().into_iter() {
-            if !queue.read().await.is_empty() {
-                let instances_starting = instance_provider
-                    .get_instances(
-                        Some(InstanceType::Server),
-                        Some(template_name),
-                        Some(EpsilonState::Starting),
-                    )
-                    .await?;
+        queue_provider.expire().await;
+        queue_provider.drop_targets().await;
 
-                let instances_ready = instance_provider
-                    .get_instances(
-                        Some(InstanceType::Server),
-                        Some(template_name),
-                        Some(EpsilonState::Running),
-                    )
-                    .await?;
+        for (queue_name, queue) in queue_provider.get_queues().into_iter() {
+            let (is_waiting, template_names) = {
+                let queue = queue.read().await;
+
+                (
+                    !queue.is_empty() && queue.get_match_settings().is_none(),
+                    queue
+                        .get_templates()
+                        .into_iter()
+                        .map(str::to_owned)
+                        .collect::<Vec<String>>(),
+                )
+            };
+
+            if is_waiting {
+                let mut instances_starting = Vec::new();
+                let mut instances_ready = Vec::new();
+
+                for template_name in &template_names {
+                    instances_starting.extend(
+                        instance_provider
+                            .get_instances(
+                                Some(InstanceType::Server),
+                                Some(template_name),
+                                Some(EpsilonState::Starting),
+                            )
+                            .await?
+                            .into_iter()
+                            .filter(|instance| !instance.is_allocated()),
+                    );
+
+                    instances_ready.extend(
+                        instance_provider
+                            .get_instances(
+                                Some(InstanceType::Server),
+                                Some(template_name),
+                                Some(EpsilonState::Running),
+                            )
+                            .await?
+                            .into_iter()
+                            .filter(|instance| !instance.is_allocated()),
+                    );
+                }
 
                 if instances_starting.is_empty() && instances_ready.is_empty() {
                     instance_provider
-                        .start_instance(template_name, None)
+                        .start_instance(&queue_provider.next_template(queue_name).await?, None)
                         .await?;
                     return Ok(());
                 }
 
-                let ready_available_slots_result = instances_ready.get_available_slots().await;
+                let mut candidates = Vec::with_capacity(instances_ready.len());
 
-                if let Ok(ready_available_slots) = ready_available_slots_result {
-                    if instances_starting.is_empty() && ready_available_slots < 1 {
-                        instance_provider
-                            .start_instance(template_name, None)
-                            .await?;
+                for instance in &instances_ready {
+                    if let Ok(available_slots) =
+                        instance_provider.get_available_slots(instance).await
+                    {
+                        candidates.push(Candidate {
+                            name: instance.get_name(),
+                            available: available_slots,
+                            slots: instance.status.as_ref().map_or(0, |status| status.slots),
+                        });
                     }
                 }
 
-                for instance in &instances_ready {
-                    if let Ok(mut available_slots) = instance.get_available_slots().await {
-                        while !queue.read().await.is_empty() && available_slots > 0 {
-                            if let Some(group) = queue.write().await.pop() {
-                                let group_size = group.players.len() as i32;
-
-                                if group_size <= available_slots {
-                                    match epsilon_api.send(SendToServer(group, instance.get_name()))
-                                    {
-                                        Ok(_) => {
-                                            available_slots -= group_size;
-                                            Ok(())
-                                        }
-                                        Err(e) => Err(e),
-                                    }?
-                                }
+                let strategy = queue_provider.get_placement_strategy();
+
+                let targets = queue.read().await.get_targets().into_iter().collect();
+                let resolved_targets = self.resolve_targets(targets, &instances_ready).await;
+
+                // A target that is full, in game or not resolved keeps its group waiting
+                let placements = queue_provider
+                    .pop_placeable(queue_name, |group| {
+                        let group_size = group.players.len() as i32;
+
+                        match &group.target {
+                            Some(target) => {
+                                resolved_targets.get(target).and_then(|instance_name| {
+                                    place_on(&mut candidates, instance_name, group_size)
+                                })
                             }
+                            None => strategy.place(&mut candidates, group_size),
+                        }
+                    })
+                    .await?;
+
+                for (group, instance_name) in placements {
+                    let players = group.players.clone();
+
+                    transfer_provider.transfer(group, &instance_name).await;
+
+                    instance_provider
+                        .reserve_slots(&instance_name, &players)
+                        .await;
+                }
+
+                if instances_starting.is_empty() {
+                    let largest_available_slots = candidates
+                        .iter()
+                        .map(|candidate| candidate.available)
+                        .max()
+                        .unwrap_or(0);
+
+                    let next_group_size = queue
+                        .read()
+                        .await
+                        .get_ordered()
+                        .into_iter()
+                        .find(|entry| entry.group.target.is_none())
+                        .map(|entry| entry.group.players.len() as i32);
+
+                    if let Some(next_group_size) = next_group_size {
+                        if next_group_size > largest_available_slots {
+                            instance_provider
+                                .start_instance(
+                                    &queue_provider.next_template(queue_name).await?,
+                                    None,
+                                )
+                                .await?;
                         }
                     }
                 }
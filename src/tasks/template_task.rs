@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::epsilon::api::common::epsilon_events::EpsilonEvent;
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::{Context, Task};
+
+pub struct TemplateTask {
+    context: Arc<Context>,
+
+    templates: HashMap<String, String>,
+}
+
+impl TemplateTask {
+    async fn fetch_templates(context: &Context) -> Result<HashMap<String, String>, EpsilonError> {
+        let mut templates = HashMap::new();
+
+        for template in context.get_template_provider().get_templates().await? {
+            let json = serde_json::to_string(&template)
+                .map_err(|error| EpsilonError::ParseJsonError(error.to_string()))?;
+
+            templates.insert(template.name, json);
+        }
+
+        Ok(templates)
+    }
+}
+
+#[async_trait]
+impl Task for TemplateTask {
+    async fn init(context: Arc<Context>) -> Result<Box<dyn Task>, EpsilonError> {
+        let templates = Self::fetch_templates(&context).await?;
+
+        Ok(Box::new(Self { context, templates }))
+    }
+
+    async fn run(&mut self) -> Result<(), EpsilonError> {
+        let templates = Self::fetch_templates(&self.context).await?;
+
+        let changed: Vec<&String> = templates
+            .iter()
+            .filter(|(name, json)| self.templates.get(*name) != Some(*json))
+            .map(|(name, _)| name)
+            .chain(
+                self.templates
+                    .keys()
+                    .filter(|name| !templates.contains_key(*name)),
+            )
+            .collect();
+
+        for template_name in changed {
+            info!("Template has changed (name={})", template_name);
+
+            self.context
+                .get_epsilon_api()
+                .send(EpsilonEvent::TemplateChanged {
+                    template: template_name.to_owned(),
+                })
+                .ok();
+        }
+
+        self.templates = templates;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Template:Task, notify when a template is created, updated or removed"
+    }
+}
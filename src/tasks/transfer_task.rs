@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::epsilon::epsilon_error::EpsilonError;
+use crate::{Context, Task};
+
+pub struct TransferTask {
+    context: Arc<Context>,
+}
+
+#[async_trait]
+impl Task for TransferTask {
+    async fn init(context: Arc<Context>) -> Result<Box<dyn Task>, EpsilonError> {
+        Ok(Box::new(Self { context }))
+    }
+
+    async fn run(&mut self) -> Result<(), EpsilonError> {
+        self.context
+            .get_transfer_provider()
+            .check_transfers(
+                self.context.get_queue_provider(),
+                self.context.get_instance_provider(),
+            )
+            .await
+    }
+
+    fn get_name(&self) -> &'static str {
+        "Transfer:Task, retry or requeue transfers not acknowledged by a proxy"
+    }
+}